@@ -0,0 +1,83 @@
+//! Base58Check encoding, as used by legacy addresses.
+
+use crate::BitcoinError;
+use crate::hashes::double_sha256;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Base58 encoding of `payload` followed by the first four bytes of its
+/// double SHA-256.
+pub fn encode_check(payload: &[u8]) -> String {
+    let mut data = payload.to_vec();
+    data.extend_from_slice(&double_sha256(payload)[..4]);
+    encode(&data)
+}
+
+fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+
+    // Base-58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut result = String::with_capacity(zeros + digits.len());
+    result.extend(core::iter::repeat_n('1', zeros));
+    result.extend(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char));
+    result
+}
+
+/// Inverse of `encode_check`: the payload with the checksum verified and
+/// stripped. Characters outside the alphabet, input too short to hold a
+/// checksum, or a checksum mismatch are `InvalidFormat`.
+pub fn decode_check(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    let mut data = decode(s)?;
+    if data.len() < 4 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+
+    let checksum = data.split_off(data.len() - 4);
+    if double_sha256(&data)[..4] != checksum[..] {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(data)
+}
+
+fn decode(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    let zeros = s.bytes().take_while(|&c| c == b'1').count();
+
+    // Base-256 bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.bytes().skip(zeros) {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or(BitcoinError::InvalidFormat)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut result = Vec::with_capacity(zeros + bytes.len());
+    result.extend(core::iter::repeat_n(0, zeros));
+    result.extend(bytes.iter().rev());
+    Ok(result)
+}
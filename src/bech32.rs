@@ -0,0 +1,154 @@
+//! Bech32 (BIP173) and Bech32m (BIP350) encoding of segwit addresses.
+
+use crate::BitcoinError;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+/// Encodes a segwit address. Version 0 programs use bech32 and later
+/// versions bech32m.
+pub fn encode(hrp: &str, witness_version: u8, program: &[u8]) -> Result<String, BitcoinError> {
+    check_program(witness_version, program)?;
+    if hrp.is_empty() || !hrp.bytes().all(|c| (33..=126).contains(&c)) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+
+    let hrp = hrp.to_ascii_lowercase();
+    let mut data = vec![witness_version];
+    data.extend(convert_bits(program, 8, 5, true)?);
+
+    let constant = checksum_constant(witness_version);
+    let mut values = hrp_expand(&hrp);
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = polymod(&values) ^ constant;
+
+    let mut result = hrp;
+    result.push('1');
+    for &d in &data {
+        result.push(CHARSET[d as usize] as char);
+    }
+    for i in 0..6 {
+        let d = (checksum >> (5 * (5 - i))) & 31;
+        result.push(CHARSET[d as usize] as char);
+    }
+    Ok(result)
+}
+
+/// Decodes a segwit address into its lowercase HRP, witness version and
+/// program. The checksum must use the constant for the decoded version,
+/// and mixed-case strings are rejected.
+pub fn decode(address: &str) -> Result<(String, u8, Vec<u8>), BitcoinError> {
+    if address.len() > 90 || !address.bytes().all(|c| (33..=126).contains(&c)) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let has_lower = address.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = address.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(BitcoinError::InvalidFormat);
+    }
+
+    let address = address.to_ascii_lowercase();
+    let separator = address.rfind('1').ok_or(BitcoinError::InvalidFormat)?;
+    let (hrp, data) = (&address[..separator], &address[separator + 1..]);
+    // At least the witness version and the six checksum characters
+    if hrp.is_empty() || data.len() < 7 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+
+    let data = data
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|&d| d == c)
+                .map(|d| d as u8)
+                .ok_or(BitcoinError::InvalidFormat)
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    let witness_version = data[0];
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    if polymod(&values) != checksum_constant(witness_version) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+
+    let program = convert_bits(&data[1..data.len() - 6], 5, 8, false)?;
+    check_program(witness_version, &program)?;
+    Ok((String::from(hrp), witness_version, program))
+}
+
+fn checksum_constant(witness_version: u8) -> u32 {
+    if witness_version == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    }
+}
+
+/// Program length rules from BIP141, plus the version 0 sizes.
+fn check_program(witness_version: u8, program: &[u8]) -> Result<(), BitcoinError> {
+    if witness_version > 16 || !(2..=40).contains(&program.len()) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    if witness_version == 0 && program.len() != 20 && program.len() != 32 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(())
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for &v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, BitcoinError> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1u32 << to) - 1;
+    let mut result = Vec::new();
+
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(result)
+}
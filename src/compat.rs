@@ -0,0 +1,56 @@
+//! Conversions to and from the `bitcoin` crate, going through the consensus
+//! encoding so bytes are preserved exactly.
+//!
+//! Outpoints and scripts always convert. Transactions use `TryFrom`, since
+//! each side's parser can reject what the other serializes, such as a
+//! transaction with no inputs.
+
+use crate::{BitcoinError, BitcoinTransaction, OutPoint, Script, Txid};
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hashes::Hash;
+
+impl From<&OutPoint> for bitcoin::OutPoint {
+    fn from(outpoint: &OutPoint) -> Self {
+        bitcoin::OutPoint {
+            txid: bitcoin::Txid::from_byte_array(outpoint.txid.0),
+            vout: outpoint.vout,
+        }
+    }
+}
+
+impl From<bitcoin::OutPoint> for OutPoint {
+    fn from(outpoint: bitcoin::OutPoint) -> Self {
+        OutPoint {
+            txid: Txid(outpoint.txid.to_byte_array()),
+            vout: outpoint.vout,
+        }
+    }
+}
+
+impl From<&Script> for bitcoin::ScriptBuf {
+    fn from(script: &Script) -> Self {
+        bitcoin::ScriptBuf::from_bytes(script.bytes.clone())
+    }
+}
+
+impl From<bitcoin::ScriptBuf> for Script {
+    fn from(script: bitcoin::ScriptBuf) -> Self {
+        Script::new(script.into_bytes())
+    }
+}
+
+impl TryFrom<&BitcoinTransaction> for bitcoin::Transaction {
+    type Error = BitcoinError;
+
+    fn try_from(tx: &BitcoinTransaction) -> Result<Self, BitcoinError> {
+        deserialize(&tx.to_bytes()).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+impl TryFrom<&bitcoin::Transaction> for BitcoinTransaction {
+    type Error = BitcoinError;
+
+    fn try_from(tx: &bitcoin::Transaction) -> Result<Self, BitcoinError> {
+        BitcoinTransaction::from_bytes_exact(&serialize(tx))
+    }
+}
@@ -0,0 +1,93 @@
+//! `Arbitrary` impls for fuzzing. Sizes are kept small so generated values
+//! stay cheap, and transactions are always ones that decode back to
+//! themselves: they have at least one input.
+
+use alloc::vec::Vec;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    BitcoinTransaction, CompactSize, OutPoint, Script, TransactionInput, TransactionOutput, Txid,
+};
+
+const MAX_SCRIPT_LEN: usize = 100;
+const MAX_INPUTS: usize = 8;
+const MAX_OUTPUTS: usize = 8;
+const MAX_WITNESS_ITEMS: usize = 4;
+const MAX_WITNESS_ITEM_LEN: usize = 80;
+
+fn bounded_bytes(u: &mut Unstructured<'_>, max_len: usize) -> Result<Vec<u8>> {
+    let len = u.int_in_range(0..=max_len)?;
+    Ok(u.bytes(len)?.to_vec())
+}
+
+fn bounded_vec<'a, T: Arbitrary<'a>>(
+    u: &mut Unstructured<'a>,
+    min_len: usize,
+    max_len: usize,
+) -> Result<Vec<T>> {
+    let len = u.int_in_range(min_len..=max_len)?;
+    (0..len).map(|_| T::arbitrary(u)).collect()
+}
+
+impl<'a> Arbitrary<'a> for CompactSize {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(CompactSize::new(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Txid {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Txid(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for OutPoint {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(OutPoint {
+            txid: u.arbitrary()?,
+            vout: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Script {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Script::new(bounded_bytes(u, MAX_SCRIPT_LEN)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for TransactionInput {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(TransactionInput::new(
+            u.arbitrary()?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for TransactionOutput {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(TransactionOutput::new(u.arbitrary()?, u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for BitcoinTransaction {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let inputs: Vec<TransactionInput> = bounded_vec(u, 1, MAX_INPUTS)?;
+        let outputs = bounded_vec(u, 0, MAX_OUTPUTS)?;
+        let mut tx = BitcoinTransaction::new(u.arbitrary()?, inputs, outputs, u.arbitrary()?);
+
+        if u.arbitrary()? {
+            for input in &mut tx.inputs {
+                let count = u.int_in_range(0..=MAX_WITNESS_ITEMS)?;
+                input.witness = (0..count)
+                    .map(|_| bounded_bytes(u, MAX_WITNESS_ITEM_LEN))
+                    .collect::<Result<Vec<_>>>()?
+                    .into();
+            }
+        }
+        Ok(tx)
+    }
+}
@@ -0,0 +1,18 @@
+//! The hash functions behind txids, merkle roots, checksums and addresses.
+
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// SHA-256 applied twice, as used for txids, block hashes and Base58Check.
+pub fn double_sha256(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+/// RIPEMD-160 of the SHA-256, as committed to by P2PKH and P2WPKH outputs.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(sha256(data)).into()
+}
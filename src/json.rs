@@ -0,0 +1,150 @@
+//! Serializable views shaped like the JSON bitcoind returns from
+//! `getrawtransaction <txid> true`.
+//!
+//! ```
+//! use rust_week_3_exercises::BitcoinTransaction;
+//! use rust_week_3_exercises::json::RawTransaction;
+//!
+//! let tx = BitcoinTransaction::new(2, vec![], vec![], 0);
+//! let json = serde_json::to_string(&RawTransaction::from(&tx)).unwrap();
+//! assert!(json.contains("\"locktime\":0"));
+//! ```
+
+use crate::{BitcoinTransaction, COIN, Script, TransactionInput, TransactionOutput};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RawTransaction {
+    pub txid: String,
+    pub hash: String,
+    pub version: u32,
+    pub size: usize,
+    pub vsize: usize,
+    pub weight: usize,
+    pub locktime: u32,
+    pub vin: Vec<Vin>,
+    pub vout: Vec<Vout>,
+    pub hex: String,
+}
+
+/// A regular input has `txid`, `vout` and `scriptSig`; the coinbase input
+/// has `coinbase` instead.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Vin {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coinbase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub txid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vout: Option<u32>,
+    #[serde(rename = "scriptSig", skip_serializing_if = "Option::is_none")]
+    pub script_sig: Option<ScriptJson>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub txinwitness: Vec<String>,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Vout {
+    /// Amount in BTC with exactly eight decimals, as bitcoind prints it.
+    /// It is kept as a string so every serde format carries it without
+    /// floating-point loss.
+    pub value: String,
+    pub n: usize,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pubkey: ScriptJson,
+}
+
+/// `asm` follows bitcoind: signatures in a scriptSig get their sighash
+/// type decoded, as in `<hex>[ALL]`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScriptJson {
+    pub asm: String,
+    pub hex: String,
+}
+
+impl ScriptJson {
+    fn script_sig(script: &Script) -> Self {
+        Self {
+            asm: script.to_asm(true),
+            hex: script.to_hex(),
+        }
+    }
+}
+
+impl From<&Script> for ScriptJson {
+    fn from(script: &Script) -> Self {
+        Self {
+            asm: script.to_asm(false),
+            hex: script.to_hex(),
+        }
+    }
+}
+
+impl Vin {
+    fn new(input: &TransactionInput, coinbase: bool) -> Self {
+        let txinwitness = input.witness.iter().map(hex::encode).collect();
+        if coinbase {
+            return Self {
+                coinbase: Some(hex::encode(&input.script_sig.bytes)),
+                txid: None,
+                vout: None,
+                script_sig: None,
+                txinwitness,
+                sequence: input.sequence,
+            };
+        }
+
+        Self {
+            coinbase: None,
+            txid: Some(input.previous_output.txid.to_rpc_string()),
+            vout: Some(input.previous_output.vout),
+            script_sig: Some(ScriptJson::script_sig(&input.script_sig)),
+            txinwitness,
+            sequence: input.sequence,
+        }
+    }
+}
+
+impl Vout {
+    fn new(n: usize, output: &TransactionOutput) -> Self {
+        Self {
+            value: format!("{}.{:08}", output.value / COIN, output.value % COIN),
+            n,
+            script_pubkey: ScriptJson::from(&output.script_pubkey),
+        }
+    }
+}
+
+impl From<&BitcoinTransaction> for RawTransaction {
+    fn from(tx: &BitcoinTransaction) -> Self {
+        let coinbase = tx.is_coinbase();
+        let vin = tx
+            .inputs
+            .iter()
+            .map(|input| Vin::new(input, coinbase))
+            .collect();
+        let vout = tx
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(n, output)| Vout::new(n, output))
+            .collect();
+
+        Self {
+            txid: tx.txid().to_rpc_string(),
+            hash: tx.wtxid().to_rpc_string(),
+            version: tx.version,
+            size: tx.size(),
+            vsize: tx.vsize(),
+            weight: tx.weight(),
+            locktime: tx.lock_time,
+            vin,
+            vout,
+            hex: tx.to_hex(),
+        }
+    }
+}
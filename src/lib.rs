@@ -1,340 +1,3220 @@
-use hex;
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod base58;
+pub mod bech32;
+#[cfg(feature = "bitcoin-compat")]
+pub mod compat;
+#[cfg(feature = "fuzz")]
+mod fuzz;
+pub mod hashes;
+#[cfg(feature = "bitcoind-json")]
+pub mod json;
+pub mod net;
+pub mod opcodes;
+pub mod testing;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::fmt;
+use core::ops::{Deref, Index};
+use core::str::FromStr;
+use hashes::double_sha256;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::fmt;
-use std::ops::Deref;
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompactSize {
     pub value: u64,
 }
 
+/// Errors from decoding and validation. `Io` only exists with the `std`
+/// feature, so the enum is non-exhaustive: matches from other crates need a
+/// wildcard arm whichever features end up enabled.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum BitcoinError {
     InsufficientBytes,
     InvalidFormat,
+    /// A well-formed transaction breaking a consensus rule, with Bitcoin
+    /// Core's reject reason.
+    InvalidTransaction(&'static str),
+    /// Wraps an error with the byte offset into the transaction buffer of
+    /// the field that failed to parse.
+    AtOffset {
+        offset: usize,
+        error: Box<BitcoinError>,
+    },
+    /// A reader failed for a reason other than running out of data.
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
 }
 
-impl CompactSize {
-    pub fn new(value: u64) -> Self {
-        Self { value }
-    }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        match self.value {
-            0..=0xFC => {
-                bytes.push(self.value as u8);
-            }
-            0xFD..=0xFFFF => {
-                bytes.push(0xFD);
-                bytes.extend_from_slice(&(self.value as u16).to_le_bytes());
-            }
-            0x10000..=0xFFFFFFFF => {
-                bytes.push(0xFE);
-                bytes.extend_from_slice(&(self.value as u32).to_le_bytes());
-            }
-            _ => {
-                bytes.push(0xFF);
-                bytes.extend_from_slice(&self.value.to_le_bytes());
+impl fmt::Display for BitcoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BitcoinError::InsufficientBytes => write!(f, "unexpected end of data"),
+            BitcoinError::InvalidFormat => write!(f, "invalid encoding"),
+            BitcoinError::InvalidTransaction(reason) => {
+                write!(f, "invalid transaction: {}", reason)
             }
+            BitcoinError::AtOffset { offset, error } => write!(f, "{} at byte {}", error, offset),
+            #[cfg(feature = "std")]
+            BitcoinError::Io(kind) => write!(f, "I/O error: {}", kind),
         }
-        bytes
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.is_empty() {
-            return Err(BitcoinError::InsufficientBytes);
+impl core::error::Error for BitcoinError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            BitcoinError::AtOffset { error, .. } => Some(&**error),
+            _ => None,
         }
+    }
+}
 
-        let first_byte = bytes[0];
-        match first_byte {
-            0..=0xFC => Ok((Self::new(first_byte as u64), 1)),
-            0xFD => {
-                if bytes.len() < 3 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let value = u16::from_le_bytes([bytes[1], bytes[2]]) as u64;
-                Ok((Self::new(value), 3))
-            }
-            0xFE => {
-                if bytes.len() < 5 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let value = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as u64;
-                Ok((Self::new(value), 5))
-            }
-            0xFF => {
-                if bytes.len() < 9 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let value = u64::from_le_bytes([
-                    bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8],
-                ]);
-                Ok((Self::new(value), 9))
-            }
+/// A reader hitting end of file is `InsufficientBytes`, like a short buffer.
+#[cfg(feature = "std")]
+impl From<std::io::Error> for BitcoinError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => BitcoinError::InsufficientBytes,
+            kind => BitcoinError::Io(kind),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Txid(pub [u8; 32]);
+impl BitcoinError {
+    /// The underlying error, with any offset wrapping removed.
+    pub fn kind(&self) -> &BitcoinError {
+        match self {
+            BitcoinError::AtOffset { error, .. } => error.kind(),
+            other => other,
+        }
+    }
 
-impl Serialize for Txid {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        let hex_string = hex::encode(&self.0);
-        serializer.serialize_str(&hex_string)
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            BitcoinError::AtOffset { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Records `offset` as the position of the failure. An offset that is
+    /// already attached is more precise and is kept.
+    pub(crate) fn at(self, offset: usize) -> Self {
+        match self {
+            BitcoinError::AtOffset { .. } => self,
+            other => BitcoinError::AtOffset {
+                offset,
+                error: Box::new(other),
+            },
+        }
     }
 }
 
-impl<'de> Deserialize<'de> for Txid {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        use serde::de::Error;
-        let s: String = Deserialize::deserialize(deserializer)?;
-        let bytes = hex::decode(&s).map_err(|_| D::Error::custom("Invalid hex string"))?;
+/// Reads `N` bytes at `offset`, returning them with the offset just past
+/// them. This is the one place fixed-width reads are bounds-checked.
+fn read_array<const N: usize>(
+    bytes: &[u8],
+    offset: usize,
+) -> Result<([u8; N], usize), BitcoinError> {
+    let end = offset
+        .checked_add(N)
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    let slice = bytes
+        .get(offset..end)
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    let mut array = [0u8; N];
+    array.copy_from_slice(slice);
+    Ok((array, end))
+}
 
-        if bytes.len() != 32 {
-            return Err(D::Error::custom("Txid must be exactly 32 bytes"));
-        }
+fn read_u16_le(bytes: &[u8], offset: usize) -> Result<(u16, usize), BitcoinError> {
+    read_array(bytes, offset).map(|(buf, next)| (u16::from_le_bytes(buf), next))
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Result<(u32, usize), BitcoinError> {
+    read_array(bytes, offset).map(|(buf, next)| (u32::from_le_bytes(buf), next))
+}
+
+fn read_u64_le(bytes: &[u8], offset: usize) -> Result<(u64, usize), BitcoinError> {
+    read_array(bytes, offset).map(|(buf, next)| (u64::from_le_bytes(buf), next))
+}
+
+/// Extra checks for decoding untrusted input. `Default` is the lenient
+/// behaviour of `from_bytes`; every violation is `InvalidFormat`. The
+/// slice decoders take options through `ByteReader::with_options`; the
+/// `consensus_decode` stream decoders always use the defaults.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParseOptions {
+    /// Reject CompactSize values not written in their shortest form.
+    pub require_canonical_compact_size: bool,
+    pub max_inputs: Option<usize>,
+    /// Longest scriptSig or scriptPubKey accepted, in bytes.
+    pub max_script_len: Option<usize>,
+    /// Accept the SegWit serialization.
+    pub allow_witness: bool,
+}
 
-        let mut array = [0u8; 32];
-        array.copy_from_slice(&bytes);
-        Ok(Txid(array))
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            require_canonical_compact_size: false,
+            max_inputs: None,
+            max_script_len: None,
+            allow_witness: true,
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct OutPoint {
-    pub txid: Txid,
-    pub vout: u32,
+/// Cursor over a byte buffer. Reads are all-or-nothing: a read that would
+/// run past the end fails with `InsufficientBytes` and leaves the position
+/// where it was.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    options: ParseOptions,
 }
 
-impl OutPoint {
-    pub fn new(txid: [u8; 32], vout: u32) -> Self {
+impl<'a> ByteReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self::with_options(buf, ParseOptions::default())
+    }
+
+    /// A reader whose decoders apply `options`.
+    pub fn with_options(buf: &'a [u8], options: ParseOptions) -> Self {
         Self {
-            txid: Txid(txid),
-            vout,
+            buf,
+            pos: 0,
+            options,
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(36);
-        bytes.extend_from_slice(&self.txid.0);
-        bytes.extend_from_slice(&self.vout.to_le_bytes());
-        bytes
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 36 {
-            return Err(BitcoinError::InsufficientBytes);
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Returns the next `n` bytes without consuming them.
+    pub fn peek(&self, n: usize) -> Option<&'a [u8]> {
+        self.buf.get(self.pos..self.pos.checked_add(n)?)
+    }
+
+    pub fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], BitcoinError> {
+        let bytes = self.peek(n).ok_or(BitcoinError::InsufficientBytes)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, BitcoinError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16_le(&mut self) -> Result<u16, BitcoinError> {
+        self.advance(read_u16_le(self.buf, self.pos))
+    }
+
+    pub fn read_u32_le(&mut self) -> Result<u32, BitcoinError> {
+        self.advance(read_u32_le(self.buf, self.pos))
+    }
+
+    pub fn read_u64_le(&mut self) -> Result<u64, BitcoinError> {
+        self.advance(read_u64_le(self.buf, self.pos))
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BitcoinError> {
+        self.advance(read_array(self.buf, self.pos))
+    }
+
+    /// Moves to the offset returned by one of the slice helpers.
+    fn advance<T>(&mut self, result: Result<(T, usize), BitcoinError>) -> Result<T, BitcoinError> {
+        let (value, next) = result?;
+        self.pos = next;
+        Ok(value)
+    }
+
+    pub fn read_compact_size(&mut self) -> Result<CompactSize, BitcoinError> {
+        let start = self.pos;
+        let result = match self.read_u8()? {
+            0xFD => self.read_u16_le().map(u64::from),
+            0xFE => self.read_u32_le().map(u64::from),
+            0xFF => self.read_u64_le(),
+            n => Ok(u64::from(n)),
+        }
+        .and_then(|value| {
+            let canonical = CompactSize::encoded_len(value) == self.pos - start;
+            if self.options.require_canonical_compact_size && !canonical {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            Ok(value)
+        });
+        if result.is_err() {
+            self.pos = start;
+        }
+        result.map(CompactSize::new)
+    }
+
+    /// Reads a CompactSize element count, failing with `InvalidFormat` when
+    /// the remaining bytes could not hold that many elements of at least
+    /// `min_item_size` bytes each. This keeps a forged count from driving
+    /// allocation.
+    pub fn read_count(&mut self, min_item_size: usize) -> Result<usize, BitcoinError> {
+        let start = self.pos;
+        let count = self.read_compact_size()?;
+        match usize::try_from(count) {
+            Ok(count) if count <= self.remaining() / min_item_size.max(1) => Ok(count),
+            _ => {
+                self.pos = start;
+                Err(BitcoinError::InvalidFormat)
+            }
         }
+    }
 
-        let mut txid = [0u8; 32];
-        txid.copy_from_slice(&bytes[0..32]);
+    pub fn read_txid(&mut self) -> Result<Txid, BitcoinError> {
+        self.read_array().map(Txid)
+    }
 
-        let mut vout_bytes = [0u8; 4];
-        vout_bytes.copy_from_slice(&bytes[32..36]);
-        let vout = u32::from_le_bytes(vout_bytes);
+    /// Reads a CompactSize length followed by that many bytes.
+    pub fn read_var_bytes(&mut self) -> Result<&'a [u8], BitcoinError> {
+        let start = self.pos;
+        let len = self.read_compact_size()?;
+        // A length beyond usize can never be satisfied by the buffer
+        let result = usize::try_from(len)
+            .map_err(|_| BitcoinError::InsufficientBytes)
+            .and_then(|len| self.read_bytes(len));
+        if result.is_err() {
+            self.pos = start;
+        }
+        result
+    }
 
-        Ok((OutPoint::new(txid, vout), 36))
+    pub fn read_script(&mut self) -> Result<Script, BitcoinError> {
+        Ok(Script::new(self.read_var_bytes()?.to_vec()))
     }
-}
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct Script {
-    pub bytes: Vec<u8>,
+    /// Like `read_script`, but borrows the script from the buffer.
+    pub fn read_script_ref(&mut self) -> Result<ScriptRef<'a>, BitcoinError> {
+        self.read_var_bytes().map(ScriptRef)
+    }
 }
 
-impl Script {
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self { bytes }
+/// Source of bytes for the decoders: a `ByteReader` over a slice or, with
+/// `std`, an `io::Read` through `IoSource`.
+pub(crate) trait ByteSource {
+    fn position(&self) -> usize;
+
+    /// The next `n` bytes, without consuming them. `Ok(None)` when fewer
+    /// than `n` bytes are left.
+    fn peek_bytes(&mut self, n: usize) -> Result<Option<&[u8]>, BitcoinError>;
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BitcoinError>;
+
+    /// A CompactSize element count, `InvalidFormat` when it cannot be
+    /// backed by elements of at least `min_item_size` bytes.
+    fn read_count(&mut self, min_item_size: usize) -> Result<usize, BitcoinError>;
+
+    /// Exactly `len` bytes.
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, BitcoinError>;
+
+    /// A CompactSize length followed by that many bytes.
+    fn read_var_vec(&mut self) -> Result<Vec<u8>, BitcoinError>;
+
+    fn options(&self) -> ParseOptions {
+        ParseOptions::default()
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = Vec::new();
-        let compact_size = CompactSize::new(self.bytes.len() as u64);
-        result.extend_from_slice(&compact_size.to_bytes());
-        result.extend_from_slice(&self.bytes);
-        result
+    fn read_u8(&mut self) -> Result<u8, BitcoinError> {
+        self.read_array().map(|[byte]| byte)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (compact_size, size_len) = CompactSize::from_bytes(bytes)?;
-        let script_len = compact_size.value as usize;
+    fn read_u16_le(&mut self) -> Result<u16, BitcoinError> {
+        self.read_array().map(u16::from_le_bytes)
+    }
 
-        if bytes.len() < size_len + script_len {
-            return Err(BitcoinError::InsufficientBytes);
-        }
+    fn read_u32_le(&mut self) -> Result<u32, BitcoinError> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    fn read_u64_le(&mut self) -> Result<u64, BitcoinError> {
+        self.read_array().map(u64::from_le_bytes)
+    }
 
-        let script_bytes = bytes[size_len..size_len + script_len].to_vec();
-        Ok((Script::new(script_bytes), size_len + script_len))
+    fn read_txid(&mut self) -> Result<Txid, BitcoinError> {
+        self.read_array().map(Txid)
+    }
+
+    fn read_compact_size(&mut self) -> Result<CompactSize, BitcoinError> {
+        let value = match self.read_u8()? {
+            0xFD => u64::from(self.read_u16_le()?),
+            0xFE => u64::from(self.read_u32_le()?),
+            0xFF => self.read_u64_le()?,
+            n => u64::from(n),
+        };
+        Ok(CompactSize::new(value))
     }
 }
 
-impl Deref for Script {
-    type Target = Vec<u8>;
-    fn deref(&self) -> &Self::Target {
-        &self.bytes
+impl ByteSource for ByteReader<'_> {
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn peek_bytes(&mut self, n: usize) -> Result<Option<&[u8]>, BitcoinError> {
+        Ok(ByteReader::peek(self, n))
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BitcoinError> {
+        ByteReader::read_array(self)
+    }
+
+    fn read_count(&mut self, min_item_size: usize) -> Result<usize, BitcoinError> {
+        ByteReader::read_count(self, min_item_size)
+    }
+
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, BitcoinError> {
+        ByteReader::read_bytes(self, len).map(<[u8]>::to_vec)
+    }
+
+    fn read_var_vec(&mut self) -> Result<Vec<u8>, BitcoinError> {
+        ByteReader::read_var_bytes(self).map(<[u8]>::to_vec)
+    }
+
+    fn read_compact_size(&mut self) -> Result<CompactSize, BitcoinError> {
+        ByteReader::read_compact_size(self)
+    }
+
+    fn options(&self) -> ParseOptions {
+        self.options
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct TransactionInput {
-    pub previous_output: OutPoint,
-    pub script_sig: Script,
-    pub sequence: u32,
+/// Upper bound on the bytes behind any count read from a stream, where the
+/// remaining length is unknown: the largest block consensus allows, as
+/// every byte weighs at least one unit.
+#[cfg(feature = "std")]
+const MAX_STREAM_BYTES: usize = MAX_BLOCK_WEIGHT;
+
+/// Reads from an `io::Read`. A premature end of stream is reported as
+/// `InsufficientBytes` and any other I/O failure as `BitcoinError::Io`.
+#[cfg(feature = "std")]
+pub(crate) struct IoSource<'a, R> {
+    reader: &'a mut R,
+    peeked: Vec<u8>,
+    pos: usize,
 }
 
-impl TransactionInput {
-    pub fn new(previous_output: OutPoint, script_sig: Script, sequence: u32) -> Self {
+#[cfg(feature = "std")]
+impl<'a, R: std::io::Read> IoSource<'a, R> {
+    pub(crate) fn new(reader: &'a mut R) -> Self {
         Self {
-            previous_output,
-            script_sig,
-            sequence,
+            reader,
+            peeked: Vec::new(),
+            pos: 0,
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.previous_output.to_bytes());
-        bytes.extend_from_slice(&self.script_sig.to_bytes());
-        bytes.extend_from_slice(&self.sequence.to_le_bytes());
-        bytes
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), BitcoinError> {
+        let from_peeked = self.peeked.len().min(buf.len());
+        buf[..from_peeked].copy_from_slice(&self.peeked[..from_peeked]);
+        self.peeked.drain(..from_peeked);
+        self.reader.read_exact(&mut buf[from_peeked..])?;
+        self.pos += buf.len();
+        Ok(())
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (outpoint, outpoint_size) = OutPoint::from_bytes(bytes)?;
+#[cfg(feature = "std")]
+impl<R: std::io::Read> ByteSource for IoSource<'_, R> {
+    fn position(&self) -> usize {
+        self.pos
+    }
 
-        if bytes.len() <= outpoint_size {
-            return Err(BitcoinError::InsufficientBytes);
+    fn peek_bytes(&mut self, n: usize) -> Result<Option<&[u8]>, BitcoinError> {
+        while self.peeked.len() < n {
+            let mut byte = [0u8];
+            match self.reader.read_exact(&mut byte) {
+                Ok(()) => self.peeked.push(byte[0]),
+                Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Ok(None);
+                }
+                Err(error) => return Err(error.into()),
+            }
         }
+        Ok(Some(&self.peeked[..n]))
+    }
 
-        let (script_sig, script_size) = Script::from_bytes(&bytes[outpoint_size..])?;
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BitcoinError> {
+        let mut array = [0u8; N];
+        self.fill(&mut array)?;
+        Ok(array)
+    }
 
-        let sequence_offset = outpoint_size + script_size;
-        if bytes.len() < sequence_offset + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    fn read_count(&mut self, min_item_size: usize) -> Result<usize, BitcoinError> {
+        let count = self.read_compact_size()?;
+        match usize::try_from(count) {
+            Ok(count) if count <= MAX_STREAM_BYTES / min_item_size.max(1) => Ok(count),
+            _ => Err(BitcoinError::InvalidFormat),
         }
+    }
 
-        let mut sequence_bytes = [0u8; 4];
-        sequence_bytes.copy_from_slice(&bytes[sequence_offset..sequence_offset + 4]);
-        let sequence = u32::from_le_bytes(sequence_bytes);
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, BitcoinError> {
+        // Grow with the data actually received rather than trusting `len`
+        let mut bytes = Vec::new();
+        let mut chunk = [0u8; 4096];
+        while bytes.len() < len {
+            let n = chunk.len().min(len - bytes.len());
+            self.fill(&mut chunk[..n])?;
+            bytes.extend_from_slice(&chunk[..n]);
+        }
+        Ok(bytes)
+    }
 
-        Ok((
-            TransactionInput::new(outpoint, script_sig, sequence),
-            sequence_offset + 4,
-        ))
+    fn read_var_vec(&mut self) -> Result<Vec<u8>, BitcoinError> {
+        let len = self.read_compact_size()?;
+        let len = usize::try_from(len).map_err(|_| BitcoinError::InsufficientBytes)?;
+        self.read_vec(len)
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct BitcoinTransaction {
-    pub version: u32,
-    pub inputs: Vec<TransactionInput>,
-    pub lock_time: u32,
+/// Destination for encoded bytes. Encoding into a `Vec<u8>` cannot fail;
+/// with `std`, any `io::Write` is used through `IoSink`.
+pub(crate) trait ByteSink {
+    type Error;
+
+    fn put(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
 }
 
-impl BitcoinTransaction {
-    pub fn new(version: u32, inputs: Vec<TransactionInput>, lock_time: u32) -> Self {
-        Self {
-            version,
-            inputs,
-            lock_time,
-        }
+impl ByteSink for Vec<u8> {
+    type Error = Infallible;
+
+    fn put(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
     }
+}
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+/// Forwards to an `io::Write`, counting the bytes written.
+#[cfg(feature = "std")]
+pub(crate) struct IoSink<'a, W> {
+    writer: &'a mut W,
+    written: usize,
+}
 
-        // Version (4 bytes, little-endian)
-        bytes.extend_from_slice(&self.version.to_le_bytes());
+#[cfg(feature = "std")]
+impl<W: std::io::Write> ByteSink for IoSink<'_, W> {
+    type Error = std::io::Error;
 
-        // Number of inputs (CompactSize)
-        let input_count = CompactSize::new(self.inputs.len() as u64);
-        bytes.extend_from_slice(&input_count.to_bytes());
+    fn put(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.writer.write_all(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+}
 
-        // Each input
-        for input in &self.inputs {
-            bytes.extend_from_slice(&input.to_bytes());
-        }
+pub(crate) fn encode_to_vec(
+    encode: impl FnOnce(&mut Vec<u8>) -> Result<(), Infallible>,
+) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let Ok(()) = encode(&mut bytes);
+    bytes
+}
 
-        // Lock time (4 bytes, little-endian)
-        bytes.extend_from_slice(&self.lock_time.to_le_bytes());
+#[cfg(feature = "std")]
+pub(crate) fn encode_to_writer<W: std::io::Write>(
+    writer: &mut W,
+    encode: impl FnOnce(&mut IoSink<'_, W>) -> std::io::Result<()>,
+) -> std::io::Result<usize> {
+    let mut sink = IoSink { writer, written: 0 };
+    encode(&mut sink)?;
+    Ok(sink.written)
+}
 
-        bytes
-    }
+/// Types with a consensus serialization.
+pub trait Encodable {
+    fn to_bytes(&self) -> Vec<u8>;
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 4 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
+/// Types that can be parsed from their consensus serialization.
+pub trait Decodable: Sized {
+    /// Fewest bytes any encoding of the type takes, used to reject element
+    /// counts the remaining input cannot hold.
+    const MIN_ENCODED_SIZE: usize = 1;
+
+    fn decode(reader: &mut ByteReader<'_>) -> Result<Self, BitcoinError>;
 
-        // Read version (4 bytes)
-        let mut version_bytes = [0u8; 4];
-        version_bytes.copy_from_slice(&bytes[0..4]);
-        let version = u32::from_le_bytes(version_bytes);
-        let mut offset = 4;
+    /// Parses a value from the start of `bytes`, returning it with the
+    /// number of bytes consumed.
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut reader = ByteReader::new(bytes);
+        let value = Self::decode(&mut reader)?;
+        Ok((value, reader.position()))
+    }
+}
+
+// Forwards the traits to the inherent methods of the same name, which take
+// precedence in path resolution.
+macro_rules! impl_consensus_codec {
+    ($($t:ty => $min_size:expr),* $(,)?) => {
+        $(
+            impl Encodable for $t {
+                fn to_bytes(&self) -> Vec<u8> {
+                    <$t>::to_bytes(self)
+                }
+            }
 
-        // Read input count
-        let (input_count, input_count_size) = CompactSize::from_bytes(&bytes[offset..])?;
-        offset += input_count_size;
+            impl Decodable for $t {
+                const MIN_ENCODED_SIZE: usize = $min_size;
 
-        // Read each input
-        let mut inputs = Vec::new();
-        for _ in 0..input_count.value {
-            if offset >= bytes.len() {
-                return Err(BitcoinError::InsufficientBytes);
+                fn decode(reader: &mut ByteReader<'_>) -> Result<Self, BitcoinError> {
+                    <$t>::decode(reader)
+                }
             }
+        )*
+    };
+}
+
+impl_consensus_codec!(
+    CompactSize => 1,
+    OutPoint => 36,
+    Script => 1,
+    Witness => 1,
+    TransactionInput => MIN_INPUT_SIZE,
+    TransactionOutput => MIN_OUTPUT_SIZE,
+    // Version, input and output counts, lock time
+    BitcoinTransaction => 4 + 1 + 1 + 4,
+);
+
+/// Most memory reserved up front for a decoded vector; longer ones grow as
+/// their elements actually parse.
+const MAX_PREALLOC_BYTES: usize = 1 << 20;
+
+/// Reads a CompactSize count followed by that many elements. The count is
+/// checked against the input left (`read_count`) and the initial allocation
+/// is capped, so a forged count cannot force a large allocation.
+pub(crate) fn decode_list<S: ByteSource, T>(
+    source: &mut S,
+    min_item_size: usize,
+    decode: impl FnMut(&mut S) -> Result<T, BitcoinError>,
+) -> Result<Vec<T>, BitcoinError> {
+    let count = source.read_count(min_item_size)?;
+    decode_items(source, count, decode)
+}
+
+/// Decodes `count` items, with the preallocation cap of `decode_list`.
+fn decode_items<S: ByteSource, T>(
+    source: &mut S,
+    count: usize,
+    mut decode: impl FnMut(&mut S) -> Result<T, BitcoinError>,
+) -> Result<Vec<T>, BitcoinError> {
+    let capacity = MAX_PREALLOC_BYTES / core::mem::size_of::<T>().max(1);
+    let mut items = Vec::with_capacity(count.min(capacity));
+    for _ in 0..count {
+        items.push(decode(source)?);
+    }
+    Ok(items)
+}
+
+/// A CompactSize count followed by each item's encoding.
+pub fn encode_vec<T: Encodable>(items: &[T]) -> Vec<u8> {
+    let mut bytes = CompactSize::new(items.len() as u64).to_bytes();
+    for item in items {
+        bytes.extend_from_slice(&item.to_bytes());
+    }
+    bytes
+}
+
+/// Parses a vector written by `encode_vec`, returning it with the number of
+/// bytes consumed.
+pub fn decode_vec<T: Decodable>(bytes: &[u8]) -> Result<(Vec<T>, usize), BitcoinError> {
+    let mut reader = ByteReader::new(bytes);
+    let items = decode_list(&mut reader, T::MIN_ENCODED_SIZE, |reader| T::decode(reader))?;
+    Ok((items, reader.position()))
+}
+
+/// Largest CompactSize Bitcoin Core accepts as a length or element count
+/// (`MAX_SIZE`, 32 MiB); no valid message can need more.
+pub const MAX_COMPACT_SIZE: u64 = 0x0200_0000;
 
-            let (input, input_size) = TransactionInput::from_bytes(&bytes[offset..])?;
-            inputs.push(input);
-            offset += input_size;
+impl CompactSize {
+    pub fn new(value: u64) -> Self {
+        Self { value }
+    }
+
+    /// Like `new`, but values above `MAX_COMPACT_SIZE` are `InvalidFormat`.
+    pub fn try_new(value: u64) -> Result<Self, BitcoinError> {
+        if value > MAX_COMPACT_SIZE {
+            return Err(BitcoinError::InvalidFormat);
         }
+        Ok(Self::new(value))
+    }
 
-        // Read lock time (4 bytes)
-        if bytes.len() < offset + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    /// Number of bytes `value` takes when encoded. The width changes just
+    /// past each prefix boundary: up to 0xFC is 1 byte, up to 0xFFFF is 3
+    /// (0xFD prefix), up to 0xFFFFFFFF is 5 (0xFE) and anything larger is 9
+    /// (0xFF).
+    pub const fn encoded_len(value: u64) -> usize {
+        match value {
+            0..=0xFC => 1,
+            0xFD..=0xFFFF => 3,
+            0x10000..=0xFFFFFFFF => 5,
+            _ => 9,
         }
-        let mut lock_time_bytes = [0u8; 4];
-        lock_time_bytes.copy_from_slice(&bytes[offset..offset + 4]);
-        let lock_time = u32::from_le_bytes(lock_time_bytes);
-        offset += 4;
+    }
 
-        Ok((BitcoinTransaction::new(version, inputs, lock_time), offset))
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
     }
-}
 
-impl fmt::Display for BitcoinTransaction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Transaction Version: {}\n", self.version)?;
-        write!(f, "Input Count: {}\n", self.inputs.len())?;
+    /// Writes the encoding to `writer`, returning the number of bytes written.
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink))
+    }
 
-        for (i, input) in self.inputs.iter().enumerate() {
-            write!(f, "Input {}:\n", i)?;
-            write!(
-                f,
-                "  Previous Output: {}\n",
-                hex::encode(&input.previous_output.txid.0)
-            )?;
-            write!(
-                f,
-                "  Previous Output Vout: {}\n",
-                input.previous_output.vout
-            )?;
-            write!(
-                f,
-                "  ScriptSig Length: {} bytes\n",
-                input.script_sig.bytes.len()
-            )?;
-            write!(f, "  ScriptSig: {}\n", hex::encode(&input.script_sig.bytes))?;
-            write!(f, "  Sequence: {}\n", input.sequence)?;
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        match self.value {
+            0..=0xFC => sink.put(&[self.value as u8]),
+            0xFD..=0xFFFF => {
+                sink.put(&[0xFD])?;
+                sink.put(&(self.value as u16).to_le_bytes())
+            }
+            0x10000..=0xFFFFFFFF => {
+                sink.put(&[0xFE])?;
+                sink.put(&(self.value as u32).to_le_bytes())
+            }
+            _ => {
+                sink.put(&[0xFF])?;
+                sink.put(&self.value.to_le_bytes())
+            }
         }
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        source.read_compact_size()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+
+    /// Reads `count` consecutive CompactSize values, returning them with the
+    /// total number of bytes consumed.
+    pub fn decode_sequence(bytes: &[u8], count: usize) -> Result<(Vec<u64>, usize), BitcoinError> {
+        let mut reader = ByteReader::new(bytes);
+        let mut values = Vec::new();
+        for _ in 0..count {
+            values.push(reader.read_compact_size()?.value);
+        }
+        Ok((values, reader.position()))
+    }
+
+    /// Like `from_bytes`, but rejects values that were not written in their
+    /// shortest form, as consensus parsing requires.
+    pub fn from_bytes_canonical(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        match Self::from_bytes_checked(bytes)? {
+            (compact_size, consumed, true) => Ok((compact_size, consumed)),
+            _ => Err(BitcoinError::InvalidFormat),
+        }
+    }
+
+    /// Like `from_bytes`, also reporting whether the value was written in
+    /// its shortest form instead of rejecting it.
+    pub fn from_bytes_checked(bytes: &[u8]) -> Result<(Self, usize, bool), BitcoinError> {
+        let (compact_size, consumed) = Self::from_bytes(bytes)?;
+        let canonical = Self::encoded_len(compact_size.value) == consumed;
+        Ok((compact_size, consumed, canonical))
+    }
+}
+
+impl From<u64> for CompactSize {
+    fn from(value: u64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<CompactSize> for u64 {
+    fn from(compact_size: CompactSize) -> Self {
+        compact_size.value
+    }
+}
+
+impl TryFrom<usize> for CompactSize {
+    type Error = BitcoinError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        u64::try_from(value)
+            .map(Self::new)
+            .map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+/// Fails when the value does not fit, e.g. above 2^32 - 1 on wasm32.
+impl TryFrom<CompactSize> for usize {
+    type Error = BitcoinError;
+
+    fn try_from(compact_size: CompactSize) -> Result<Self, Self::Error> {
+        usize::try_from(compact_size.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+/// Length of `len` bytes once prefixed with their CompactSize length.
+fn var_bytes_len(len: usize) -> usize {
+    CompactSize::encoded_len(len as u64) + len
+}
+
+fn checked_sum(mut values: impl Iterator<Item = u64>) -> Result<u64, BitcoinError> {
+    values.try_fold(0u64, |total, value| {
+        total.checked_add(value).ok_or(BitcoinError::InvalidFormat)
+    })
+}
+
+/// Ordered and hashed by the raw bytes in internal order.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct Txid(pub [u8; 32]);
+
+impl Txid {
+    /// The 32 bytes in internal order; any other length is `InvalidFormat`.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        bytes
+            .try_into()
+            .map(Txid)
+            .map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Hex in the reversed byte order used by RPC and block explorers.
+    pub fn to_rpc_string(&self) -> String {
+        let mut reversed = self.0;
+        reversed.reverse();
+        hex::encode(reversed)
+    }
+
+    /// Parses reversed (RPC) hex back into the internal byte order.
+    pub fn from_rpc_string(s: &str) -> Result<Self, BitcoinError> {
+        let bytes = hex::decode(s).map_err(|_| BitcoinError::InvalidFormat)?;
+        let mut txid = Self::from_slice(&bytes)?;
+        txid.0.reverse();
+        Ok(txid)
+    }
+
+    /// Parses a txid written in display (big-endian) order, as copied from
+    /// an explorer. Same as `from_rpc_string`.
+    pub fn from_hex_be(s: &str) -> Result<Self, BitcoinError> {
+        Self::from_rpc_string(s)
+    }
+
+    /// Display (big-endian) hex. Same as `to_rpc_string`.
+    pub fn to_hex_be(&self) -> String {
+        self.to_rpc_string()
+    }
+}
+
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_rpc_string())
+    }
+}
+
+impl FromStr for Txid {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_rpc_string(s)
+    }
+}
+
+/// Bitcoin merkle root of `txids`, duplicating the last node of odd levels.
+/// `None` for an empty slice.
+pub fn merkle_root(txids: &[Txid]) -> Option<Txid> {
+    let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.0).collect();
+    if level.is_empty() {
+        return None;
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&pair[0]);
+                let mut concat = [0u8; 64];
+                concat[..32].copy_from_slice(&pair[0]);
+                concat[32..].copy_from_slice(right);
+                double_sha256(&concat)
+            })
+            .collect();
+    }
+    Some(Txid(level[0]))
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Txid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let hex_string = hex::encode(self.0);
+        serializer.serialize_str(&hex_string)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Txid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let s: String = Deserialize::deserialize(deserializer)?;
+        let bytes = hex::decode(&s).map_err(|_| D::Error::custom("Invalid hex string"))?;
+
+        Txid::from_slice(&bytes).map_err(|_| D::Error::custom("Txid must be exactly 32 bytes"))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OutPoint {
+    pub txid: Txid,
+    pub vout: u32,
+}
+
+impl OutPoint {
+    pub fn new(txid: [u8; 32], vout: u32) -> Self {
+        Self {
+            txid: Txid(txid),
+            vout,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink))
+    }
+
+    /// The 32-byte txid then the little-endian vout. Debug builds check the
+    /// vout bytes against `vout_wire_bytes` to catch an endianness flip.
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        let vout = self.vout.to_le_bytes();
+        debug_assert_eq!(vout, Self::vout_wire_bytes(self.vout));
+        sink.put(&self.txid.0)?;
+        sink.put(&vout)
+    }
+
+    /// The wire bytes of a vout, least significant first, built by shifting
+    /// rather than through the encoder.
+    fn vout_wire_bytes(vout: u32) -> [u8; 4] {
+        [
+            vout as u8,
+            (vout >> 8) as u8,
+            (vout >> 16) as u8,
+            (vout >> 24) as u8,
+        ]
+    }
+
+    /// The all-zero txid with vout 0xFFFFFFFF spent by coinbase inputs.
+    pub fn null() -> Self {
+        Self::new([0u8; 32], u32::MAX)
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Self::null()
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    /// Debug builds check the decoded vout the same way as `write_to`.
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        let txid = source.read_txid()?;
+        let bytes = source.read_array::<4>()?;
+        let vout = u32::from_le_bytes(bytes);
+        debug_assert_eq!(bytes, Self::vout_wire_bytes(vout));
+        Ok(Self { txid, vout })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+}
+
+/// The null outpoint.
+impl Default for OutPoint {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+/// `txid:vout`, with the txid in RPC byte order.
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.vout)
+    }
+}
+
+impl FromStr for OutPoint {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (txid, vout) = s.split_once(':').ok_or(BitcoinError::InvalidFormat)?;
+        Ok(Self {
+            txid: txid.parse()?,
+            vout: vout.parse().map_err(|_| BitcoinError::InvalidFormat)?,
+        })
+    }
+}
+
+/// A script. `raw_bytes` (or deref) is the script alone; `to_bytes` and
+/// `to_wire_bytes` add the CompactSize length used on the wire.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Script {
+    pub bytes: Vec<u8>,
+}
+
+impl Script {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// The script itself, without a length prefix. Same as `&*script`.
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The CompactSize length followed by the script. Same as `to_bytes`.
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    /// Parses bare script hex, as RPC prints a scriptPubKey. There is no
+    /// length prefix, unlike `from_bytes`.
+    pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+        hex::decode(s)
+            .map(Script::new)
+            .map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    /// Bare script hex, without the length prefix.
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.bytes)
+    }
+
+    /// The wire form, with the CompactSize length prefix.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink))
+    }
+
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        self.as_ref_view().write_to(sink)
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    /// A declared length the remaining input cannot hold, or one above
+    /// `max_script_len`, is `InvalidFormat` before any script bytes are read.
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        let start = source.position();
+        let len = source.read_count(1)?;
+        if source.options().max_script_len.is_some_and(|max| len > max) {
+            return Err(BitcoinError::InvalidFormat.at(start));
+        }
+        source.read_vec(len).map(Script::new)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+
+    pub fn builder() -> ScriptBuilder {
+        ScriptBuilder::new()
+    }
+
+    /// Borrowed view of the script bytes.
+    pub fn as_ref_view(&self) -> ScriptRef<'_> {
+        ScriptRef(&self.bytes)
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        self.as_ref_view().instructions()
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        self.as_ref_view().script_type()
+    }
+
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        self.as_ref_view().witness_program()
+    }
+
+    pub fn op_return_data(&self) -> Option<&[u8]> {
+        self.as_ref_view().op_return_data()
+    }
+
+    pub fn parse_multisig(&self) -> Option<(u8, Vec<Vec<u8>>)> {
+        self.as_ref_view().parse_multisig()
+    }
+
+    /// A copy with every OP_CODESEPARATOR removed. Bytes after a malformed
+    /// push are kept as they are.
+    pub(crate) fn without_code_separators(&self) -> Script {
+        let mut bytes = Vec::with_capacity(self.bytes.len());
+        let mut instructions = self.instructions();
+        loop {
+            let start = instructions.reader.position();
+            match instructions.next() {
+                None => break,
+                Some(Ok(Instruction::Op(opcodes::OP_CODESEPARATOR))) => {}
+                Some(Ok(_)) => {
+                    let end = instructions.reader.position();
+                    bytes.extend_from_slice(&self.bytes[start..end]);
+                }
+                Some(Err(_)) => {
+                    bytes.extend_from_slice(&self.bytes[start..]);
+                    break;
+                }
+            }
+        }
+        Script::new(bytes)
+    }
+
+    pub fn parse_pushes(&self) -> Result<Vec<Vec<u8>>, BitcoinError> {
+        self.as_ref_view().parse_pushes()
+    }
+
+    pub fn sigop_count(&self, accurate: bool) -> usize {
+        self.as_ref_view().sigop_count(accurate)
+    }
+
+    pub fn disassemble(&self) -> Result<Vec<String>, BitcoinError> {
+        self.as_ref_view().disassemble()
+    }
+
+    pub fn to_asm(&self, decode_sighash: bool) -> String {
+        self.as_ref_view().to_asm(decode_sighash)
+    }
+
+    /// Evaluates `script_sig` against this P2PKH scriptPubKey: pushes the
+    /// signature and pubkey, then runs DUP HASH160 <hash> EQUALVERIFY
+    /// CHECKSIG. CHECKSIG calls `verify_sig(signature, pubkey)` with the
+    /// sighash type byte already removed from the signature.
+    ///
+    /// `sighash` is the `SIGHASH_ALL` digest of the spending input, as from
+    /// `sighash_legacy`, and `verify_sig` should check the signature
+    /// against it. A signature whose type byte is anything but
+    /// `SIGHASH_ALL` committed to a different digest and fails without
+    /// reaching `verify_sig`.
+    ///
+    /// Scripts outside that template are `InvalidFormat`; a pubkey hash
+    /// mismatch or failed signature check is `Ok(false)`.
+    pub fn verify_p2pkh(
+        &self,
+        script_sig: &Script,
+        _sighash: &[u8; 32],
+        verify_sig: impl Fn(&[u8], &[u8]) -> bool,
+    ) -> Result<bool, BitcoinError> {
+        if self.script_type() != ScriptType::P2PKH {
+            return Err(BitcoinError::InvalidFormat);
+        }
+
+        let mut stack = Vec::new();
+        for instruction in script_sig.instructions() {
+            match instruction? {
+                Instruction::PushBytes(data) => stack.push(data),
+                Instruction::Op(_) => return Err(BitcoinError::InvalidFormat),
+            }
+        }
+        let [signature, pubkey] = stack[..] else {
+            return Err(BitcoinError::InvalidFormat);
+        };
+
+        // OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY
+        if hashes::hash160(pubkey)[..] != self.bytes[3..23] {
+            return Ok(false);
+        }
+
+        // OP_CHECKSIG
+        match signature.split_last() {
+            Some((&sighash_type, signature)) if u32::from(sighash_type) == SIGHASH_ALL => {
+                Ok(verify_sig(signature, pubkey))
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Borrowed script bytes, for read-only analysis without copying out of
+/// the buffer being parsed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScriptRef<'a>(pub &'a [u8]);
+
+impl<'a> ScriptRef<'a> {
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// The script with its CompactSize length prefix, as in `Script::to_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        CompactSize::new(self.0.len() as u64).write_to(sink)?;
+        sink.put(self.0)
+    }
+
+    pub fn to_script(&self) -> Script {
+        Script::new(self.0.to_vec())
+    }
+
+    pub fn instructions(&self) -> Instructions<'a> {
+        Instructions {
+            reader: ByteReader::new(self.0),
+        }
+    }
+
+    /// Classifies the script by matching the standard output templates
+    /// byte for byte.
+    pub fn script_type(&self) -> ScriptType {
+        use opcodes::*;
+
+        match self.0 {
+            [
+                OP_DUP,
+                OP_HASH160,
+                0x14,
+                hash @ ..,
+                OP_EQUALVERIFY,
+                OP_CHECKSIG,
+            ] if hash.len() == 20 => ScriptType::P2PKH,
+            [OP_HASH160, 0x14, hash @ .., OP_EQUAL] if hash.len() == 20 => ScriptType::P2SH,
+            [OP_0, 0x14, program @ ..] if program.len() == 20 => ScriptType::P2WPKH,
+            [OP_0, 0x20, program @ ..] if program.len() == 32 => ScriptType::P2WSH,
+            [OP_1, 0x20, program @ ..] if program.len() == 32 => ScriptType::P2TR,
+            [OP_RETURN, ..] => ScriptType::OpReturn,
+            _ => ScriptType::NonStandard,
+        }
+    }
+
+    /// The witness version and program of a segwit output script: a
+    /// version opcode followed by a single 2 to 40 byte push.
+    pub fn witness_program(&self) -> Option<(u8, &'a [u8])> {
+        let (&version_op, rest) = self.0.split_first()?;
+        let version = match version_op {
+            opcodes::OP_0 => 0,
+            opcodes::OP_1..=opcodes::OP_16 => version_op - opcodes::OP_1 + 1,
+            _ => return None,
+        };
+        let (&len, program) = rest.split_first()?;
+        if len as usize != program.len() || !(2..=40).contains(&program.len()) {
+            return None;
+        }
+        Some((version, program))
+    }
+
+    /// The payload of an `OP_RETURN <push>` script, or `None` for any other
+    /// shape.
+    pub fn op_return_data(&self) -> Option<&'a [u8]> {
+        let rest = self.0.strip_prefix(&[opcodes::OP_RETURN])?;
+        let mut instructions = ScriptRef(rest).instructions();
+        match (instructions.next(), instructions.next()) {
+            (Some(Ok(Instruction::PushBytes(data))), None) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// The required signature count and pubkeys of an `OP_m <pubkey>...
+    /// OP_n OP_CHECKMULTISIG` script. Pubkeys must be 33 or 65 bytes, n must
+    /// match their number and 1 <= m <= n.
+    pub fn parse_multisig(&self) -> Option<(u8, Vec<Vec<u8>>)> {
+        use opcodes::*;
+
+        let small_int = |instruction| match instruction {
+            Some(Ok(Instruction::Op(op @ OP_1..=OP_16))) => Some(op - OP_1 + 1),
+            _ => None,
+        };
+        let rest = self.0.strip_suffix(&[OP_CHECKMULTISIG])?;
+        let mut instructions = ScriptRef(rest).instructions();
+        let m = small_int(instructions.next())?;
+
+        let mut pubkeys = Vec::new();
+        let n = loop {
+            match instructions.next() {
+                Some(Ok(Instruction::PushBytes(key))) if matches!(key.len(), 33 | 65) => {
+                    pubkeys.push(key.to_vec());
+                }
+                other => break small_int(other)?,
+            }
+        };
+        let valid = instructions.next().is_none() && usize::from(n) == pubkeys.len() && m <= n;
+        valid.then_some((m, pubkeys))
+    }
+
+    /// Signature operations as counted by consensus: one per CHECKSIG(VERIFY)
+    /// and 20 per CHECKMULTISIG(VERIFY), or in `accurate` mode the key
+    /// count from a directly preceding OP_1..OP_16. Counting stops at the
+    /// first malformed push.
+    pub fn sigop_count(&self, accurate: bool) -> usize {
+        use opcodes::*;
+
+        let mut count = 0;
+        let mut last_op = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else { break };
+            let op = match instruction {
+                Instruction::Op(op) => Some(op),
+                Instruction::PushBytes(_) => None,
+            };
+            match op {
+                Some(OP_CHECKSIG | OP_CHECKSIGVERIFY) => count += 1,
+                Some(OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY) => {
+                    count += match last_op {
+                        Some(n @ OP_1..=OP_16) if accurate => usize::from(n - OP_1 + 1),
+                        _ => 20,
+                    };
+                }
+                _ => {}
+            }
+            last_op = op;
+        }
+        count
+    }
+
+    /// The data elements pushed by the script, skipping other opcodes. For a
+    /// P2PKH scriptSig this is `[signature, pubkey]`.
+    pub fn parse_pushes(&self) -> Result<Vec<Vec<u8>>, BitcoinError> {
+        let mut pushes = Vec::new();
+        for instruction in self.instructions() {
+            if let Instruction::PushBytes(data) = instruction? {
+                pushes.push(data.to_vec());
+            }
+        }
+        Ok(pushes)
+    }
+
+    /// The script in bitcoind's `asm` notation. Pushes of up to four bytes
+    /// are shown as numbers, longer ones as lowercase hex, and OP_1NEGATE and
+    /// OP_1..OP_16 as -1 and 1..16. With `decode_sighash`, as bitcoind does
+    /// for scriptSigs, a DER signature with a known sighash byte is shown as
+    /// `<hex>[ALL]` and so on. A malformed push ends the output with
+    /// `[error]`.
+    pub fn to_asm(&self, decode_sighash: bool) -> String {
+        use opcodes::*;
+
+        let decode_sighash = decode_sighash && self.0.first() != Some(&OP_RETURN);
+        let mut tokens = Vec::new();
+        for instruction in self.instructions() {
+            let token = match instruction {
+                Err(_) => {
+                    tokens.push("[error]".to_string());
+                    break;
+                }
+                Ok(Instruction::PushBytes(data)) if data.len() <= 4 => {
+                    script_num_decode(data).to_string()
+                }
+                Ok(Instruction::PushBytes(data)) => {
+                    let sighash_type = data
+                        .split_last()
+                        .filter(|_| decode_sighash && is_der_signature(data))
+                        .and_then(|(&last, sig)| Some((SighashType::from_u32(last.into())?, sig)));
+                    match sighash_type {
+                        Some((sighash_type, sig)) => {
+                            format!("{}[{}]", hex::encode(sig), sighash_type.asm_name())
+                        }
+                        None => hex::encode(data),
+                    }
+                }
+                Ok(Instruction::Op(OP_1NEGATE)) => "-1".to_string(),
+                Ok(Instruction::Op(op @ OP_1..=OP_16)) => (op - OP_1 + 1).to_string(),
+                Ok(Instruction::Op(op)) => opcodes::name(op).unwrap_or("OP_UNKNOWN").to_string(),
+            };
+            tokens.push(token);
+        }
+        tokens.join(" ")
+    }
+
+    /// Human-readable opcode tokens, with pushed data shown as `<N bytes>`.
+    /// A push that runs past the end of the script is `InvalidFormat`.
+    pub fn disassemble(&self) -> Result<Vec<String>, BitcoinError> {
+        self.instructions()
+            .map(|instruction| {
+                Ok(match instruction? {
+                    Instruction::PushBytes([]) => "OP_0".to_string(),
+                    Instruction::PushBytes(data) => format!("<{} bytes>", data.len()),
+                    Instruction::Op(op) => match opcodes::name(op) {
+                        Some(name) => name.to_string(),
+                        None => format!("OP_UNKNOWN(0x{:02x})", op),
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBuilder {
+    bytes: Vec<u8>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_opcode(mut self, opcode: u8) -> Self {
+        self.bytes.push(opcode);
+        self
+    }
+
+    /// Pushes `data` with the smallest push opcode that can hold it.
+    pub fn push_slice(mut self, data: &[u8]) -> Self {
+        let len = data.len();
+        if len <= 0x4b {
+            self.bytes.push(len as u8);
+        } else if len <= 0xff {
+            self.bytes.push(opcodes::OP_PUSHDATA1);
+            self.bytes.push(len as u8);
+        } else if len <= 0xffff {
+            self.bytes.push(opcodes::OP_PUSHDATA2);
+            self.bytes.extend_from_slice(&(len as u16).to_le_bytes());
+        } else {
+            self.bytes.push(opcodes::OP_PUSHDATA4);
+            self.bytes.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    /// Pushes a number using OP_0, OP_1NEGATE and OP_1..OP_16 where
+    /// possible, otherwise a minimally encoded script number.
+    pub fn push_int(self, n: i64) -> Self {
+        match n {
+            0 => self.push_opcode(opcodes::OP_0),
+            -1 => self.push_opcode(opcodes::OP_1NEGATE),
+            1..=16 => self.push_opcode(opcodes::OP_1 + (n as u8) - 1),
+            _ => {
+                let encoded = script_num_encode(n);
+                self.push_slice(&encoded)
+            }
+        }
+    }
+
+    pub fn into_script(self) -> Script {
+        Script::new(self.bytes)
+    }
+}
+
+/// Little-endian sign-magnitude encoding with no redundant bytes.
+fn script_num_encode(n: i64) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut abs = n.unsigned_abs();
+    while abs > 0 {
+        result.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+
+    // The top bit of the last byte is the sign
+    if let Some(last) = result.last_mut() {
+        if *last & 0x80 != 0 {
+            result.push(if n < 0 { 0x80 } else { 0x00 });
+        } else if n < 0 {
+            *last |= 0x80;
+        }
+    }
+    result
+}
+
+/// Reads a little-endian sign-magnitude number of at most 8 bytes, without
+/// requiring minimal encoding.
+fn script_num_decode(bytes: &[u8]) -> i64 {
+    let Some((&last, _)) = bytes.split_last() else {
+        return 0;
+    };
+    let mut magnitude = 0u64;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let byte = if i == bytes.len() - 1 {
+            byte & 0x7f
+        } else {
+            byte
+        };
+        magnitude |= u64::from(byte) << (8 * i);
+    }
+    let magnitude = magnitude as i64;
+    if last & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Strict DER signature encoding with a trailing sighash byte, as BIP66
+/// requires (`IsValidSignatureEncoding` in Bitcoin Core).
+fn is_der_signature(sig: &[u8]) -> bool {
+    if !(9..=73).contains(&sig.len()) || sig[0] != 0x30 || usize::from(sig[1]) != sig.len() - 3 {
+        return false;
+    }
+    let len_r = usize::from(sig[3]);
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = usize::from(sig[5 + len_r]);
+    if len_r + len_s + 7 != sig.len() || sig[2] != 0x02 || sig[4 + len_r] != 0x02 {
+        return false;
+    }
+
+    // Each integer is non-empty, positive and without excess zero padding
+    let valid_int = |int: &[u8]| match int {
+        [] => false,
+        [first, ..] if first & 0x80 != 0 => false,
+        [0x00, second, ..] => second & 0x80 != 0,
+        _ => true,
+    };
+    valid_int(&sig[4..4 + len_r]) && valid_int(&sig[6 + len_r..6 + len_r + len_s])
+}
+
+/// Derefs to the raw script bytes, which unlike `to_bytes` carry no length
+/// prefix.
+impl Deref for Script {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
+
+/// The disassembly, or the raw hex when the script does not parse.
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.disassemble() {
+            Ok(tokens) => write!(f, "{}", tokens.join(" ")),
+            Err(_) => write!(f, "{}", hex::encode(&self.bytes)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScriptType {
+    P2PKH,
+    P2SH,
+    P2WPKH,
+    P2WSH,
+    P2TR,
+    OpReturn,
+    NonStandard,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    /// Data pushed by OP_0, a direct push or one of the OP_PUSHDATA opcodes.
+    PushBytes(&'a [u8]),
+    Op(u8),
+}
+
+/// Iterator over the instructions of a script. Stops after the first error.
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    reader: ByteReader<'a>,
+}
+
+impl<'a> Instructions<'a> {
+    fn next_instruction(&mut self) -> Result<Instruction<'a>, BitcoinError> {
+        let reader = &mut self.reader;
+        let len = match reader.read_u8()? {
+            opcodes::OP_0 => 0,
+            op @ 0x01..=0x4b => op as usize,
+            opcodes::OP_PUSHDATA1 => reader.read_u8()? as usize,
+            opcodes::OP_PUSHDATA2 => reader.read_u16_le()? as usize,
+            opcodes::OP_PUSHDATA4 => reader.read_u32_le()? as usize,
+            op => return Ok(Instruction::Op(op)),
+        };
+        Ok(Instruction::PushBytes(reader.read_bytes(len)?))
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.is_empty() {
+            return None;
+        }
+
+        let result = self.next_instruction();
+        if result.is_err() {
+            // Skip the rest so iteration ends after reporting the error
+            let remaining = self.reader.remaining();
+            let _ = self.reader.read_bytes(remaining);
+        }
+        Some(result.map_err(|_| BitcoinError::InvalidFormat))
+    }
+}
+
+/// Bit 31: when set the sequence carries no relative lock time.
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+/// Bit 22: when set the lock time is in 512-second units, else blocks.
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000FFFF;
+
+/// The witness stack of one input. `to_bytes` is the CompactSize item count
+/// followed by each item with its CompactSize length.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct Witness {
+    items: Vec<Vec<u8>>,
+}
+
+impl Witness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, item: Vec<u8>) {
+        self.items.push(item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Vec<u8>> {
+        self.items.iter()
+    }
+
+    pub fn as_slice(&self) -> &[Vec<u8>] {
+        &self.items
+    }
+
+    /// The taproot annex: a last item starting with 0x50, when there are at
+    /// least two items.
+    pub fn taproot_annex(&self) -> Option<&[u8]> {
+        match self.items.as_slice() {
+            [_, .., last] if last.first() == Some(&0x50) => Some(last),
+            _ => None,
+        }
+    }
+
+    /// Classifies the witness as a taproot key-path or script-path spend,
+    /// ignoring any annex. `None` when it fits neither shape. Only the
+    /// witness is checked, so a P2WSH witness can look like a script path.
+    pub fn taproot_spend_type(&self) -> Option<TaprootSpend> {
+        let items = match self.taproot_annex() {
+            Some(_) => &self.items[..self.items.len() - 1],
+            None => &self.items[..],
+        };
+        match items {
+            [] => None,
+            [sig] => matches!(sig.len(), 64 | 65).then_some(TaprootSpend::KeyPath),
+            [.., control] => {
+                // Leaf version (0xc0) with the parity bit, internal key and
+                // up to 128 merkle path hashes
+                let path_len = control.len().checked_sub(33)?;
+                let valid = control[0] & 0xFE == 0xC0 && path_len % 32 == 0 && path_len <= 128 * 32;
+                valid.then_some(TaprootSpend::ScriptPath)
+            }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    /// Length of `to_bytes` without building it.
+    pub fn serialized_len(&self) -> usize {
+        CompactSize::encoded_len(self.items.len() as u64)
+            + self
+                .items
+                .iter()
+                .map(|item| var_bytes_len(item.len()))
+                .sum::<usize>()
+    }
+
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        CompactSize::new(self.items.len() as u64).write_to(sink)?;
+        for item in &self.items {
+            CompactSize::new(item.len() as u64).write_to(sink)?;
+            sink.put(item)?;
+        }
+        Ok(())
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        // Each item takes at least its one-byte length prefix
+        let items = decode_list(source, 1, S::read_var_vec)?;
+        Ok(Self { items })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+}
+
+/// How a witness spends a taproot output (BIP341).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TaprootSpend {
+    /// A single 64-byte signature, or 65 bytes with an explicit sighash type.
+    KeyPath,
+    /// Script inputs, the tapscript and a control block.
+    ScriptPath,
+}
+
+impl From<Vec<Vec<u8>>> for Witness {
+    fn from(items: Vec<Vec<u8>>) -> Self {
+        Self { items }
+    }
+}
+
+impl Index<usize> for Witness {
+    type Output = Vec<u8>;
+
+    fn index(&self, index: usize) -> &Vec<u8> {
+        &self.items[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Witness {
+    type Item = &'a Vec<u8>;
+    type IntoIter = core::slice::Iter<'a, Vec<u8>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransactionInput {
+    pub previous_output: OutPoint,
+    pub script_sig: Script,
+    pub sequence: u32,
+    /// Witness stack, empty for legacy inputs. Not part of `to_bytes`; the
+    /// transaction serializes it after the outputs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub witness: Witness,
+}
+
+/// Spends the null outpoint with an empty scriptSig and final sequence.
+impl Default for TransactionInput {
+    fn default() -> Self {
+        Self::new(OutPoint::null(), Script::default(), u32::MAX)
+    }
+}
+
+impl TransactionInput {
+    pub fn new(previous_output: OutPoint, script_sig: Script, sequence: u32) -> Self {
+        Self {
+            previous_output,
+            script_sig,
+            sequence,
+            witness: Witness::new(),
+        }
+    }
+
+    pub fn push_witness_item(&mut self, item: Vec<u8>) {
+        self.witness.push(item);
+    }
+
+    /// A copy with `script_sig` replaced.
+    pub fn with_script(&self, script_sig: Script) -> TransactionInput {
+        TransactionInput {
+            script_sig,
+            ..self.clone()
+        }
+    }
+
+    /// A copy with an empty `script_sig`, as other inputs appear in a legacy
+    /// sighash preimage.
+    pub fn with_empty_script(&self) -> TransactionInput {
+        self.with_script(Script::default())
+    }
+
+    pub fn witness_items(&self) -> &[Vec<u8>] {
+        self.witness.as_slice()
+    }
+
+    pub fn has_witness(&self) -> bool {
+        !self.witness.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink))
+    }
+
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        self.previous_output.write_to(sink)?;
+        self.script_sig.write_to(sink)?;
+        sink.put(&self.sequence.to_le_bytes())
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.sequence == 0xFFFFFFFF
+    }
+
+    /// BIP125 opt-in replace-by-fee signal.
+    pub fn enables_rbf(&self) -> bool {
+        self.sequence < 0xFFFFFFFE
+    }
+
+    /// Decodes the BIP68 relative lock time. Returns `None` when the disable
+    /// flag (bit 31) is set, which includes the final sequence 0xFFFFFFFF.
+    /// Only the type flag and the low 16 bits are meaningful; the other
+    /// bits are ignored. BIP68 only applies to version 2+ transactions.
+    pub fn relative_locktime(&self) -> Option<RelativeLockTime> {
+        if self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return None;
+        }
+
+        let value = (self.sequence & SEQUENCE_LOCKTIME_MASK) as u16;
+        if self.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            Some(RelativeLockTime::Time(value))
+        } else {
+            Some(RelativeLockTime::Blocks(value))
+        }
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        let previous_output = OutPoint::decode_from(source)?;
+        let script_sig = Script::decode_from(source)?;
+        let sequence = source.read_u32_le()?;
+        Ok(TransactionInput::new(previous_output, script_sig, sequence))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+}
+
+/// A BIP68 relative lock time decoded from an input's sequence number.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    /// In units of 512 seconds.
+    Time(u16),
+}
+
+impl RelativeLockTime {
+    pub fn seconds(&self) -> Option<u32> {
+        match self {
+            RelativeLockTime::Blocks(_) => None,
+            RelativeLockTime::Time(intervals) => Some(u32::from(*intervals) * 512),
+        }
+    }
+}
+
+/// Satoshis per bitcoin.
+pub const COIN: u64 = 100_000_000;
+
+/// The 21 million BTC supply cap, in satoshis.
+pub const MAX_MONEY: u64 = 21_000_000 * COIN;
+
+/// Blocks between subsidy halvings.
+pub const HALVING_INTERVAL: u32 = 210_000;
+
+/// The new coins a block at `height` may create: 50 BTC, halved every
+/// 210,000 blocks until it reaches zero.
+pub fn block_subsidy(height: u32) -> u64 {
+    let halvings = height / HALVING_INTERVAL;
+    if halvings >= 64 {
+        return 0;
+    }
+    (50 * COIN) >> halvings
+}
+
+/// An amount in satoshis. Displays as BTC with eight decimals, computed
+/// with integer arithmetic.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct Amount(pub u64);
+
+impl Amount {
+    pub fn from_sat(sats: u64) -> Amount {
+        Amount(sats)
+    }
+
+    pub fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    /// `InvalidFormat` for negative or non-finite values, amounts above
+    /// `MAX_MONEY`, and values that are not a whole number of satoshis.
+    pub fn from_btc(btc: f64) -> Result<Amount, BitcoinError> {
+        if !btc.is_finite() || btc < 0.0 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let sats = btc * COIN as f64;
+        if sats > (MAX_MONEY + 1) as f64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        // Round to the nearest satoshi (`f64::round` needs std); a whole
+        // number of satoshis converts back to exactly the input
+        let sats = (sats + 0.5) as u64;
+        if sats > MAX_MONEY || sats as f64 / COIN as f64 != btc {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Amount(sats))
+    }
+
+    /// Lossy for amounts too precise for an `f64`; use for display only.
+    pub fn to_btc(self) -> f64 {
+        self.0 as f64 / COIN as f64
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:08} BTC", self.0 / COIN, self.0 % COIN)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TransactionOutput {
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn amount(&self) -> Amount {
+        Amount(self.value)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink))
+    }
+
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        sink.put(&self.value.to_le_bytes())?;
+        self.script_pubkey.write_to(sink)
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        let value = source.read_u64_le()?;
+        let script_pubkey = Script::decode_from(source)?;
+        Ok(TransactionOutput::new(value, script_pubkey))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+
+    /// The address paying to `script_pubkey`, or `None` when the script is
+    /// not a P2PKH, P2SH or valid witness program.
+    pub fn address(&self, network: Network) -> Option<String> {
+        let script = &self.script_pubkey;
+        match script.script_type() {
+            ScriptType::P2PKH => {
+                let mut payload = vec![network.p2pkh_prefix()];
+                payload.extend_from_slice(&script[3..23]);
+                Some(base58::encode_check(&payload))
+            }
+            ScriptType::P2SH => {
+                let mut payload = vec![network.p2sh_prefix()];
+                payload.extend_from_slice(&script[2..22]);
+                Some(base58::encode_check(&payload))
+            }
+            ScriptType::OpReturn => None,
+            _ => {
+                let (version, program) = script.witness_program()?;
+                bech32::encode(network.bech32_hrp(), version, program).ok()
+            }
+        }
+    }
+
+    /// Bitcoin Core's dust threshold: the fee, at `dust_relay_fee_per_kvb`,
+    /// for this output plus a typical input spending it (148 bytes, or 67
+    /// virtual bytes for witness programs). OP_RETURN outputs are never
+    /// dust, so their threshold is 0.
+    pub fn dust_threshold(&self, dust_relay_fee_per_kvb: u64) -> u64 {
+        let spend_size = match self.script_pubkey.script_type() {
+            ScriptType::OpReturn => return 0,
+            _ if self.script_pubkey.witness_program().is_some() => 32 + 4 + 1 + 107 / 4 + 4,
+            _ => 32 + 4 + 1 + 107 + 4,
+        };
+        let size = 8 + var_bytes_len(self.script_pubkey.len()) + spend_size;
+        size as u64 * dust_relay_fee_per_kvb / 1000
+    }
+
+    pub fn is_dust(&self, dust_relay_fee_per_kvb: u64) -> bool {
+        self.value < self.dust_threshold(dust_relay_fee_per_kvb)
+    }
+
+    /// `is_dust` at Bitcoin Core's default `DUST_RELAY_TX_FEE`.
+    pub fn is_dust_default(&self) -> bool {
+        self.is_dust(DUST_RELAY_TX_FEE)
+    }
+}
+
+/// Bitcoin Core's default dust relay fee in sat/kvB, three times the
+/// minimum relay fee.
+pub const DUST_RELAY_TX_FEE: u64 = 3000;
+
+/// Value in satoshis and BTC, then the script hex and its detected type.
+impl fmt::Display for TransactionOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sat ({}), ScriptPubKey: {} ({:?})",
+            self.value,
+            self.amount(),
+            hex::encode(&self.script_pubkey.bytes),
+            self.script_pubkey.script_type()
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+    Signet,
+}
+
+impl Network {
+    pub fn p2pkh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet | Network::Regtest | Network::Signet => 0x6f,
+        }
+    }
+
+    pub fn p2sh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet | Network::Regtest | Network::Signet => 0xc4,
+        }
+    }
+
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+
+    /// P2P message start bytes. Signet uses the default signet challenge.
+    pub fn magic(&self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0xf9, 0xbe, 0xb4, 0xd9],
+            Network::Testnet => [0x0b, 0x11, 0x09, 0x07],
+            Network::Regtest => [0xfa, 0xbf, 0xb5, 0xda],
+            Network::Signet => [0x0a, 0x03, 0xcf, 0x40],
+        }
+    }
+
+    pub fn from_magic(magic: &[u8; 4]) -> Option<Network> {
+        [
+            Network::Mainnet,
+            Network::Testnet,
+            Network::Regtest,
+            Network::Signet,
+        ]
+        .into_iter()
+        .find(|network| network.magic() == *magic)
+    }
+}
+
+/// Lock times below this are block heights, at or above it Unix times.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LockTime {
+    Blocks(u32),
+    Time(u32),
+}
+
+impl LockTime {
+    pub fn from_u32(lock_time: u32) -> Self {
+        if lock_time < LOCKTIME_THRESHOLD {
+            LockTime::Blocks(lock_time)
+        } else {
+            LockTime::Time(lock_time)
+        }
+    }
+
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            LockTime::Blocks(n) | LockTime::Time(n) => *n,
+        }
+    }
+}
+
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// The standard signature hash types.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SighashType {
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+}
+
+impl SighashType {
+    /// `None` for any value other than the six standard flags.
+    pub fn from_u32(value: u32) -> Option<SighashType> {
+        Some(match value {
+            SIGHASH_ALL => SighashType::All,
+            SIGHASH_NONE => SighashType::None,
+            SIGHASH_SINGLE => SighashType::Single,
+            0x81 => SighashType::AllPlusAnyoneCanPay,
+            0x82 => SighashType::NonePlusAnyoneCanPay,
+            0x83 => SighashType::SinglePlusAnyoneCanPay,
+            _ => return None,
+        })
+    }
+
+    pub fn to_u32(self) -> u32 {
+        let anyone_can_pay = if self.is_anyonecanpay() {
+            SIGHASH_ANYONECANPAY
+        } else {
+            0
+        };
+        let base = match self.base_type() {
+            SighashType::None => SIGHASH_NONE,
+            SighashType::Single => SIGHASH_SINGLE,
+            _ => SIGHASH_ALL,
+        };
+        base | anyone_can_pay
+    }
+
+    pub fn is_anyonecanpay(self) -> bool {
+        matches!(
+            self,
+            SighashType::AllPlusAnyoneCanPay
+                | SighashType::NonePlusAnyoneCanPay
+                | SighashType::SinglePlusAnyoneCanPay
+        )
+    }
+
+    /// The name bitcoind prints after a signature in `asm`, such as
+    /// `ALL|ANYONECANPAY`.
+    pub fn asm_name(self) -> &'static str {
+        match self {
+            SighashType::All => "ALL",
+            SighashType::None => "NONE",
+            SighashType::Single => "SINGLE",
+            SighashType::AllPlusAnyoneCanPay => "ALL|ANYONECANPAY",
+            SighashType::NonePlusAnyoneCanPay => "NONE|ANYONECANPAY",
+            SighashType::SinglePlusAnyoneCanPay => "SINGLE|ANYONECANPAY",
+        }
+    }
+
+    /// The type with the ANYONECANPAY modifier removed.
+    pub fn base_type(self) -> SighashType {
+        match self {
+            SighashType::All | SighashType::AllPlusAnyoneCanPay => SighashType::All,
+            SighashType::None | SighashType::NonePlusAnyoneCanPay => SighashType::None,
+            SighashType::Single | SighashType::SinglePlusAnyoneCanPay => SighashType::Single,
+        }
+    }
+}
+
+/// Policy limit on the weight of a standard transaction.
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+/// Consensus limit on block weight.
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+/// Largest standard scriptSig, enough for a 15-of-15 multisig spend.
+const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1650;
+
+/// Outpoint, empty script and sequence.
+const MIN_INPUT_SIZE: usize = 36 + 1 + 4;
+/// Value and empty script.
+const MIN_OUTPUT_SIZE: usize = 8 + 1;
+
+/// Serialized size of each part of a transaction, in bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SizeBreakdown {
+    pub version: usize,
+    pub input_count_varint: usize,
+    pub inputs: usize,
+    pub output_count_varint: usize,
+    pub outputs: usize,
+    /// Marker, flag and witness stacks; 0 without witness data.
+    pub witness: usize,
+    pub locktime: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BitcoinTransaction {
+    pub version: u32,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+    pub lock_time: u32,
+}
+
+impl BitcoinTransaction {
+    /// Arguments follow wire order: version, inputs, outputs, lock_time.
+    pub fn new(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TransactionOutput>,
+        lock_time: u32,
+    ) -> Self {
+        Self {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        }
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(TransactionInput::has_witness)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.serialize(self.has_witness())
+    }
+
+    /// The pre-SegWit serialization: no marker, flag or witness data, even
+    /// when inputs carry witnesses.
+    pub fn to_bytes_legacy(&self) -> Vec<u8> {
+        self.serialize(false)
+    }
+
+    /// A copy with every input's witness cleared.
+    pub fn strip_witness(&self) -> BitcoinTransaction {
+        let mut tx = self.clone();
+        for input in &mut tx.inputs {
+            input.witness.clear();
+        }
+        tx
+    }
+
+    /// A copy with each input replaced by `f(index, input)`.
+    pub fn map_inputs(
+        &self,
+        mut f: impl FnMut(usize, &TransactionInput) -> TransactionInput,
+    ) -> BitcoinTransaction {
+        BitcoinTransaction {
+            inputs: self
+                .inputs_with_index()
+                .map(|(i, input)| f(i, input))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Whether both transactions hash to the same txid, so differ at most
+    /// in witness data. Unlike `eq_ignore_witness` this hashes both sides.
+    pub fn same_txid(&self, other: &Self) -> bool {
+        self.txid() == other.txid()
+    }
+
+    /// Equality of everything the txid commits to: version, inputs apart
+    /// from their witnesses, outputs and lock time.
+    pub fn eq_ignore_witness(&self, other: &Self) -> bool {
+        let input_eq = |a: &TransactionInput, b: &TransactionInput| {
+            a.previous_output == b.previous_output
+                && a.script_sig == b.script_sig
+                && a.sequence == b.sequence
+        };
+        self.version == other.version
+            && self.inputs.len() == other.inputs.len()
+            && self
+                .inputs
+                .iter()
+                .zip(&other.inputs)
+                .all(|(a, b)| input_eq(a, b))
+            && self.outputs == other.outputs
+            && self.lock_time == other.lock_time
+    }
+
+    /// Writes the transaction to `writer` without building an intermediate
+    /// buffer, returning the number of bytes written.
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink, self.has_witness()))
+    }
+
+    /// The `Display` output with the value and mainnet address each input
+    /// spends, where `prevouts[i]` is the output spent by input `i`. Missing
+    /// prevouts and scripts without an address show as `<unknown>`.
+    pub fn display_with_prevouts(&self, prevouts: &[TransactionOutput]) -> String {
+        TransactionDisplay {
+            tx: self,
+            prevouts: Some(prevouts),
+        }
+        .to_string()
+    }
+
+    /// Hash of the legacy serialization, which excludes marker, flag and
+    /// witness data (BIP141).
+    pub fn txid(&self) -> Txid {
+        Txid(double_sha256(&self.to_bytes_legacy()))
+    }
+
+    /// Hash of the full serialization, including witness data when present.
+    pub fn wtxid(&self) -> Txid {
+        Txid(double_sha256(&self.to_bytes()))
+    }
+
+    pub fn lock_time_parsed(&self) -> LockTime {
+        LockTime::from_u32(self.lock_time)
+    }
+
+    /// Consensus finality (`IsFinalTx`) for inclusion in a block at `height`.
+    /// `mtp` is the median time past: the median timestamp of the previous
+    /// 11 blocks, which BIP113 uses instead of the block's own time for
+    /// time-based lock times. A transaction is final when its lock time is
+    /// 0, already passed, or every input has the final sequence.
+    pub fn is_final(&self, height: u32, mtp: u32) -> bool {
+        let passed = match self.lock_time_parsed() {
+            LockTime::Blocks(0) => return true,
+            LockTime::Blocks(n) => n < height,
+            LockTime::Time(t) => t < mtp,
+        };
+        passed || self.inputs.iter().all(TransactionInput::is_final)
+    }
+
+    /// Marks the transaction replaceable (BIP125) by setting every input
+    /// with sequence 0xFFFFFFFF or 0xFFFFFFFE to 0xFFFFFFFD. Other sequences
+    /// already signal RBF, and may carry a relative lock time, so they are
+    /// left untouched.
+    pub fn signal_rbf(&mut self) {
+        for input in &mut self.inputs {
+            if !input.enables_rbf() {
+                input.sequence = 0xFFFFFFFD;
+            }
+        }
+    }
+
+    /// Undoes RBF signaling by setting inputs that signal it without a
+    /// relative lock time to 0xFFFFFFFE, which keeps the lock time enabled.
+    /// Inputs with a relative lock time always signal RBF and are left
+    /// untouched.
+    pub fn clear_rbf(&mut self) {
+        for input in &mut self.inputs {
+            if input.enables_rbf() && input.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+                input.sequence = 0xFFFFFFFE;
+            }
+        }
+    }
+
+    /// Sequence numbers carry BIP68 relative lock times only from version 2.
+    pub fn is_bip68_active(&self) -> bool {
+        self.version >= 2
+    }
+
+    /// The relative lock time of input `input_index`, `None` when the input
+    /// does not exist, has it disabled, or the transaction predates BIP68.
+    pub fn input_relative_locktime(&self, input_index: usize) -> Option<RelativeLockTime> {
+        if !self.is_bip68_active() {
+            return None;
+        }
+        self.inputs.get(input_index)?.relative_locktime()
+    }
+
+    pub fn exceeds_standard_weight(&self) -> bool {
+        self.weight() > MAX_STANDARD_TX_WEIGHT
+    }
+
+    /// Whether the transaction passes the subset of Bitcoin Core's
+    /// standardness policy checked by `standardness_error`.
+    pub fn is_standard(&self) -> bool {
+        self.standardness_error().is_none()
+    }
+
+    /// The first standardness rule the transaction breaks, using Bitcoin
+    /// Core's reject reason. Checks the version, non-empty inputs and
+    /// outputs, weight, scriptSig sizes and output script types.
+    pub fn standardness_error(&self) -> Option<&'static str> {
+        if !(1..=2).contains(&self.version) {
+            return Some("version");
+        }
+        if self.inputs.is_empty() {
+            return Some("bad-txns-vin-empty");
+        }
+        if self.outputs.is_empty() {
+            return Some("bad-txns-vout-empty");
+        }
+        if self.exceeds_standard_weight() {
+            return Some("tx-size");
+        }
+        if self
+            .inputs
+            .iter()
+            .any(|input| input.script_sig.len() > MAX_STANDARD_SCRIPTSIG_SIZE)
+        {
+            return Some("scriptsig-size");
+        }
+        if self
+            .outputs
+            .iter()
+            .any(|output| output.script_pubkey.script_type() == ScriptType::NonStandard)
+        {
+            return Some("scriptpubkey");
+        }
+        None
+    }
+
+    /// Context-free consensus checks, without looking up spent outputs:
+    /// non-empty inputs and outputs, no output or output total above
+    /// `MAX_MONEY`, and no outpoint spent twice.
+    pub fn validate_structure(&self) -> Result<(), BitcoinError> {
+        if self.inputs.is_empty() {
+            return Err(BitcoinError::InvalidTransaction("bad-txns-vin-empty"));
+        }
+        if self.outputs.is_empty() {
+            return Err(BitcoinError::InvalidTransaction("bad-txns-vout-empty"));
+        }
+        if self.outputs.iter().any(|output| output.value > MAX_MONEY) {
+            return Err(BitcoinError::InvalidTransaction("bad-txns-vout-toolarge"));
+        }
+        match self.total_output_value() {
+            Ok(total) if total <= MAX_MONEY => {}
+            _ => {
+                return Err(BitcoinError::InvalidTransaction(
+                    "bad-txns-txouttotal-toolarge",
+                ));
+            }
+        }
+        let mut spent = BTreeSet::new();
+        if !self
+            .spent_outpoints()
+            .all(|outpoint| spent.insert(outpoint))
+        {
+            return Err(BitcoinError::InvalidTransaction(
+                "bad-txns-inputs-duplicate",
+            ));
+        }
+        Ok(())
+    }
+
+    /// A coinbase has a single input spending the null outpoint.
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    /// The BIP34 block height from the first push of a coinbase scriptSig.
+    /// `None` for other transactions or when that push is not a minimally
+    /// encoded non-negative number. Coinbases from before BIP34 (mainnet
+    /// block 227,931) carry arbitrary data there, which may still decode.
+    pub fn coinbase_height(&self) -> Option<u32> {
+        if !self.is_coinbase() {
+            return None;
+        }
+
+        match self.input(0)?.script_sig.instructions().next()?.ok()? {
+            Instruction::Op(op @ opcodes::OP_1..=opcodes::OP_16) => {
+                Some(u32::from(op - opcodes::OP_1) + 1)
+            }
+            Instruction::Op(_) => None,
+            Instruction::PushBytes([]) => Some(0),
+            Instruction::PushBytes(data) if data.len() <= 4 => {
+                let mut le = [0u8; 4];
+                le[..data.len()].copy_from_slice(data);
+                let height = u32::from_le_bytes(le);
+                (script_num_encode(i64::from(height)) == data).then_some(height)
+            }
+            Instruction::PushBytes(_) => None,
+        }
+    }
+
+    /// Sorts inputs and outputs into BIP69 order. Inputs compare by txid in
+    /// display byte order, then vout; outputs by value, then script bytes.
+    pub fn sort_bip69(&mut self) {
+        self.inputs.sort_by(|a, b| {
+            let (a, b) = (&a.previous_output, &b.previous_output);
+            a.txid
+                .0
+                .iter()
+                .rev()
+                .cmp(b.txid.0.iter().rev())
+                .then(a.vout.cmp(&b.vout))
+        });
+
+        self.outputs.sort_by(|a, b| {
+            a.value
+                .cmp(&b.value)
+                .then_with(|| a.script_pubkey.bytes.cmp(&b.script_pubkey.bytes))
+        });
+    }
+
+    /// Copy of the transaction in BIP69 order.
+    pub fn sorted_bip69(&self) -> Self {
+        let mut tx = self.clone();
+        tx.sort_bip69();
+        tx
+    }
+
+    /// BIP143 signature hash for spending the segwit v0 output `input_index`
+    /// with the given script code and amount. Use a `SighashCache` when
+    /// signing several inputs.
+    pub fn sighash_segwit(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        value: u64,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        SighashCache::new(self).segwit_signature_hash(input_index, script_code, value, sighash_type)
+    }
+
+    /// Pre-segwit signature hash for spending `input_index`, whose previous
+    /// output locks to `script_pubkey` (or the redeem script for P2SH).
+    /// OP_CODESEPARATORs are removed from the script as consensus does.
+    ///
+    /// SIGHASH_SINGLE with no output at `input_index` returns the value 1
+    /// (a one byte followed by 31 zeros) instead of an error, matching the
+    /// behaviour consensus has to preserve.
+    pub fn sighash_legacy(
+        &self,
+        input_index: usize,
+        script_pubkey: &Script,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        self.input(input_index).ok_or(BitcoinError::InvalidFormat)?;
+        let anyone_can_pay = sighash_type.is_anyonecanpay();
+        let base_type = sighash_type.base_type();
+
+        let own_output = self.output(input_index);
+        if base_type == SighashType::Single && own_output.is_none() {
+            let mut one = [0u8; 32];
+            one[0] = 1;
+            return Ok(one);
+        }
+
+        let script_code = script_pubkey.without_code_separators();
+        let mut tx = BitcoinTransaction::new(self.version, Vec::new(), Vec::new(), self.lock_time);
+
+        for (i, input) in self.inputs.iter().enumerate() {
+            if anyone_can_pay && i != input_index {
+                continue;
+            }
+
+            let mut input = if i == input_index {
+                input.with_script(script_code.clone())
+            } else {
+                input.with_empty_script()
+            };
+            // Other inputs may be updated freely under NONE and SINGLE
+            if i != input_index && base_type != SighashType::All {
+                input.sequence = 0;
+            }
+            tx.inputs.push(input);
+        }
+
+        tx.outputs = match base_type {
+            SighashType::None => Vec::new(),
+            SighashType::Single => {
+                // Outputs before ours are blanked to value -1 and an empty script
+                let mut outputs =
+                    vec![TransactionOutput::new(u64::MAX, Script::new(Vec::new())); input_index];
+                outputs.extend(own_output.cloned());
+                outputs
+            }
+            _ => self.outputs.clone(),
+        };
+
+        let mut preimage = tx.serialize(false);
+        preimage.extend_from_slice(&sighash_type.to_u32().to_le_bytes());
+        Ok(double_sha256(&preimage))
+    }
+
+    /// Full serialized length in bytes, including any witness data.
+    pub fn size(&self) -> usize {
+        self.base_size() + self.witness_size()
+    }
+
+    /// Length of `to_bytes`, computed without encoding. Same as `size`.
+    pub fn serialized_len(&self) -> usize {
+        self.size()
+    }
+
+    /// BIP141 weight: non-witness bytes count four times, witness bytes
+    /// (including marker and flag) once.
+    pub fn weight(&self) -> usize {
+        self.base_size() * 4 + self.witness_size()
+    }
+
+    /// Virtual size, `weight / 4` rounded up.
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Input `i`, or `None` when out of range.
+    pub fn input(&self, i: usize) -> Option<&TransactionInput> {
+        self.inputs.get(i)
+    }
+
+    /// Output `i`, or `None` when out of range.
+    pub fn output(&self, i: usize) -> Option<&TransactionOutput> {
+        self.outputs.get(i)
+    }
+
+    pub fn inputs_with_index(&self) -> impl Iterator<Item = (usize, &TransactionInput)> {
+        self.inputs.iter().enumerate()
+    }
+
+    pub fn outputs_with_index(&self) -> impl Iterator<Item = (usize, &TransactionOutput)> {
+        self.outputs.iter().enumerate()
+    }
+
+    /// Legacy sigop count over every scriptSig and scriptPubKey, as used for
+    /// the block sigop limit before P2SH and witness sigops are added.
+    pub fn total_sigops(&self) -> usize {
+        let script_sigs = self.inputs.iter().map(|input| &input.script_sig);
+        let script_pubkeys = self.outputs.iter().map(|output| &output.script_pubkey);
+        script_sigs
+            .chain(script_pubkeys)
+            .map(|script| script.sigop_count(false))
+            .sum()
+    }
+
+    /// The previous output of each input, in input order.
+    pub fn spent_outpoints(&self) -> impl Iterator<Item = &OutPoint> {
+        self.inputs.iter().map(|input| &input.previous_output)
+    }
+
+    /// Sum of all output values. Returns `Err(InvalidFormat)` rather than
+    /// wrapping or panicking when crafted values overflow a `u64`.
+    pub fn total_output_value(&self) -> Result<u64, BitcoinError> {
+        checked_sum(self.outputs.iter().map(|output| output.value))
+    }
+
+    /// Input value minus output value. `prevouts[i]` is the output spent by
+    /// input `i`. A length mismatch, overflow, or outputs exceeding inputs is
+    /// `InvalidFormat`.
+    pub fn fee(&self, prevouts: &[TransactionOutput]) -> Result<u64, BitcoinError> {
+        if prevouts.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+
+        let input_value = checked_sum(prevouts.iter().map(|prevout| prevout.value))?;
+        let output_value = self.total_output_value()?;
+        input_value
+            .checked_sub(output_value)
+            .ok_or(BitcoinError::InvalidFormat)
+    }
+
+    /// Fee rate in sat/vB.
+    pub fn fee_rate(&self, prevouts: &[TransactionOutput]) -> Result<f64, BitcoinError> {
+        Ok(self.fee(prevouts)? as f64 / self.vsize() as f64)
+    }
+
+    /// Sizes of the serialization's parts, summed from the fields without
+    /// encoding anything.
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| 36 + var_bytes_len(input.script_sig.len()) + 4)
+            .sum();
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| 8 + var_bytes_len(output.script_pubkey.len()))
+            .sum();
+
+        let mut breakdown = SizeBreakdown {
+            version: 4,
+            input_count_varint: CompactSize::encoded_len(self.inputs.len() as u64),
+            inputs,
+            output_count_varint: CompactSize::encoded_len(self.outputs.len() as u64),
+            outputs,
+            witness: self.witness_size(),
+            locktime: 4,
+            total: 0,
+        };
+        breakdown.total = breakdown.version
+            + breakdown.input_count_varint
+            + breakdown.inputs
+            + breakdown.output_count_varint
+            + breakdown.outputs
+            + breakdown.witness
+            + breakdown.locktime;
+        breakdown
+    }
+
+    fn base_size(&self) -> usize {
+        let breakdown = self.size_breakdown();
+        breakdown.total - breakdown.witness
+    }
+
+    fn witness_size(&self) -> usize {
+        if !self.has_witness() {
+            return 0;
+        }
+
+        let stacks: usize = self
+            .inputs
+            .iter()
+            .map(|input| input.witness.serialized_len())
+            .sum();
+
+        // Marker and flag
+        2 + stacks
+    }
+
+    fn serialize(&self, segwit: bool) -> Vec<u8> {
+        let len = if segwit {
+            self.serialized_len()
+        } else {
+            self.base_size()
+        };
+        let mut bytes = Vec::with_capacity(len);
+        let Ok(()) = self.write_to(&mut bytes, segwit);
+        bytes
+    }
+
+    fn write_to<S: ByteSink>(&self, sink: &mut S, segwit: bool) -> Result<(), S::Error> {
+        // Version (4 bytes, little-endian)
+        sink.put(&self.version.to_le_bytes())?;
+
+        // SegWit marker and flag
+        if segwit {
+            sink.put(&[0x00, 0x01])?;
+        }
+
+        // Number of inputs (CompactSize)
+        CompactSize::new(self.inputs.len() as u64).write_to(sink)?;
+
+        // Each input
+        for input in &self.inputs {
+            input.write_to(sink)?;
+        }
+
+        // Number of outputs (CompactSize)
+        CompactSize::new(self.outputs.len() as u64).write_to(sink)?;
+
+        // Each output
+        for output in &self.outputs {
+            output.write_to(sink)?;
+        }
+
+        // Witness stack for each input
+        if segwit {
+            for input in &self.inputs {
+                input.witness.write_to(sink)?;
+            }
+        }
+
+        // Lock time (4 bytes, little-endian)
+        sink.put(&self.lock_time.to_le_bytes())
+    }
+
+    /// On failure the error carries the reader position of the field that
+    /// could not be parsed.
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    /// Reads one transaction off a stream without knowing its length in
+    /// advance. Offsets in errors count the bytes consumed from `reader`.
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        Self::decode_fields(source).map_err(|e| e.at(source.position()))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (tx, rest) = Self::from_bytes_with_remainder(bytes)?;
+        Ok((tx, bytes.len() - rest.len()))
+    }
+
+    /// Parses a transaction from the start of `bytes`, returning it with the
+    /// unconsumed tail, ready for the next parse.
+    pub fn from_bytes_with_remainder(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let mut reader = ByteReader::new(bytes);
+        let tx = Self::decode(&mut reader)?;
+        Ok((tx, &bytes[reader.position()..]))
+    }
+
+    /// Like `from_bytes`, with the stricter checks enabled in `options`.
+    pub fn from_bytes_with_options(
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let mut reader = ByteReader::with_options(bytes, *options);
+        let tx = Self::decode(&mut reader)?;
+        Ok((tx, reader.position()))
+    }
+
+    /// Parses `count` back-to-back transactions, returning them with the
+    /// total number of bytes consumed.
+    pub fn parse_many(bytes: &[u8], count: usize) -> Result<(Vec<Self>, usize), BitcoinError> {
+        let mut reader = ByteReader::new(bytes);
+        let mut transactions = Vec::new();
+        for _ in 0..count {
+            transactions.push(Self::decode(&mut reader)?);
+        }
+        Ok((transactions, reader.position()))
+    }
+
+    /// Parses the transaction section of a block: a CompactSize count, then
+    /// that many transactions. A count more bytes than remain could hold is
+    /// rejected before anything is allocated.
+    pub fn parse_block_transactions(bytes: &[u8]) -> Result<(Vec<Self>, usize), BitcoinError> {
+        decode_vec(bytes)
+    }
+
+    /// Like `from_bytes`, but trailing bytes after the transaction are an
+    /// `InvalidFormat` error.
+    pub fn from_bytes_exact(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        let (tx, consumed) = Self::from_bytes(bytes)?;
+        if consumed != bytes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(tx)
+    }
+
+    /// Parses a raw transaction from hex; the whole input must be consumed.
+    pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+        let bytes = hex::decode(s).map_err(|_| BitcoinError::InvalidFormat)?;
+        Self::from_bytes_exact(&bytes)
+    }
+
+    /// Like `from_hex`, but ignores ASCII whitespace such as the line breaks
+    /// in hex pasted from a terminal.
+    pub fn from_hex_lenient(s: &str) -> Result<Self, BitcoinError> {
+        let compact: String = s.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        Self::from_hex(&compact)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// The serialization as hex, one field per line with the byte offset and
+    /// a comment naming the field, e.g. `0000  02000000  # version`.
+    pub fn annotated_hex(&self) -> String {
+        let mut out = String::new();
+        let mut offset = 0;
+        let mut line = |bytes: &[u8], label: &str| {
+            if !bytes.is_empty() {
+                out.push_str(&format!(
+                    "{:04x}  {}  # {}\n",
+                    offset,
+                    hex::encode(bytes),
+                    label
+                ));
+                offset += bytes.len();
+            }
+        };
+        let count = |n: usize| CompactSize::new(n as u64).to_bytes();
+
+        line(&self.version.to_le_bytes(), "version");
+        let segwit = self.has_witness();
+        if segwit {
+            line(&[0x00, 0x01], "segwit marker and flag");
+        }
+
+        line(&count(self.inputs.len()), "input count");
+        for (i, input) in self.inputs_with_index() {
+            let script = &input.script_sig;
+            line(&input.previous_output.txid.0, &format!("input {} txid", i));
+            line(
+                &input.previous_output.vout.to_le_bytes(),
+                &format!("input {} vout", i),
+            );
+            line(
+                &count(script.len()),
+                &format!("input {} scriptSig length", i),
+            );
+            line(script, &format!("input {} scriptSig", i));
+            line(
+                &input.sequence.to_le_bytes(),
+                &format!("input {} sequence", i),
+            );
+        }
+
+        line(&count(self.outputs.len()), "output count");
+        for (i, output) in self.outputs_with_index() {
+            let script = &output.script_pubkey;
+            line(&output.value.to_le_bytes(), &format!("output {} value", i));
+            line(
+                &count(script.len()),
+                &format!("output {} scriptPubKey length", i),
+            );
+            line(script, &format!("output {} scriptPubKey", i));
+        }
+
+        if segwit {
+            for (i, input) in self.inputs_with_index() {
+                line(
+                    &count(input.witness.len()),
+                    &format!("input {} witness item count", i),
+                );
+                for (j, item) in input.witness.iter().enumerate() {
+                    line(
+                        &count(item.len()),
+                        &format!("input {} witness {} length", i, j),
+                    );
+                    line(item, &format!("input {} witness {}", i, j));
+                }
+            }
+        }
+
+        line(&self.lock_time.to_le_bytes(), "lock time");
+        debug_assert_eq!(offset, self.serialized_len());
+        out
+    }
+
+    fn decode_fields<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        let version = source.read_u32_le()?;
+
+        // Per BIP144 a 0x00 here is always the SegWit marker and must be
+        // followed by a 0x01 flag; a legacy zero input count is not valid.
+        let marker_pos = source.position();
+        let segwit = source.peek_bytes(1)? == Some(&[0x00]);
+        if segwit {
+            if !source.options().allow_witness {
+                return Err(BitcoinError::InvalidFormat.at(marker_pos));
+            }
+            let [_, flag] = source.read_array::<2>()?;
+            if flag != 0x01 {
+                return Err(BitcoinError::InvalidFormat.at(marker_pos + 1));
+            }
+        }
+
+        // The input limit is checked before any input is decoded
+        let input_count_pos = source.position();
+        let input_count = source.read_count(MIN_INPUT_SIZE)?;
+        if source
+            .options()
+            .max_inputs
+            .is_some_and(|max| input_count > max)
+        {
+            return Err(BitcoinError::InvalidFormat.at(input_count_pos));
+        }
+        let mut inputs = decode_items(source, input_count, TransactionInput::decode_from)?;
+        let outputs = decode_list(source, MIN_OUTPUT_SIZE, TransactionOutput::decode_from)?;
+
+        if segwit {
+            for input in &mut inputs {
+                input.witness = Witness::decode_from(source)?;
+            }
+
+            // The marker is only allowed when there is witness data to mark
+            if !inputs.iter().any(TransactionInput::has_witness) {
+                return Err(BitcoinError::InvalidFormat.at(marker_pos));
+            }
+        }
+
+        let lock_time = source.read_u32_le()?;
+
+        Ok(BitcoinTransaction::new(version, inputs, outputs, lock_time))
+    }
+}
+
+/// Version 2 with no inputs or outputs and lock time 0.
+impl Default for BitcoinTransaction {
+    fn default() -> Self {
+        Self::new(2, Vec::new(), Vec::new(), 0)
+    }
+}
+
+/// The BIP143 hashes of all prevouts, sequences and outputs, computed once
+/// so signing every input of a transaction stays linear.
+#[derive(Debug, Clone)]
+pub struct SighashCache<'a> {
+    tx: &'a BitcoinTransaction,
+    hash_prevouts: [u8; 32],
+    hash_sequence: [u8; 32],
+    hash_outputs: [u8; 32],
+}
+
+impl<'a> SighashCache<'a> {
+    pub fn new(tx: &'a BitcoinTransaction) -> Self {
+        let mut prevouts = Vec::with_capacity(36 * tx.inputs.len());
+        let mut sequences = Vec::with_capacity(4 * tx.inputs.len());
+        for input in &tx.inputs {
+            prevouts.extend_from_slice(&input.previous_output.to_bytes());
+            sequences.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        let mut outputs = Vec::new();
+        for output in &tx.outputs {
+            outputs.extend_from_slice(&output.to_bytes());
+        }
+
+        Self {
+            tx,
+            hash_prevouts: double_sha256(&prevouts),
+            hash_sequence: double_sha256(&sequences),
+            hash_outputs: double_sha256(&outputs),
+        }
+    }
+
+    /// Same as `BitcoinTransaction::sighash_segwit`. ANYONECANPAY zeroes the
+    /// prevouts and sequences hashes, NONE and SINGLE zero the sequences,
+    /// and SINGLE hashes only the output at `input_index` (zero if absent).
+    pub fn segwit_signature_hash(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        value: u64,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        let tx = self.tx;
+        let input = tx.input(input_index).ok_or(BitcoinError::InvalidFormat)?;
+        let anyone_can_pay = sighash_type.is_anyonecanpay();
+        let base_type = sighash_type.base_type();
+
+        let hash_prevouts = if anyone_can_pay {
+            [0u8; 32]
+        } else {
+            self.hash_prevouts
+        };
+
+        let hash_sequence = if anyone_can_pay || base_type != SighashType::All {
+            [0u8; 32]
+        } else {
+            self.hash_sequence
+        };
+
+        let hash_outputs = match (base_type, tx.output(input_index)) {
+            (SighashType::All, _) => self.hash_outputs,
+            (SighashType::Single, Some(output)) => double_sha256(&output.to_bytes()),
+            _ => [0u8; 32],
+        };
+
+        let mut preimage = Vec::new();
+        preimage.extend_from_slice(&tx.version.to_le_bytes());
+        preimage.extend_from_slice(&hash_prevouts);
+        preimage.extend_from_slice(&hash_sequence);
+        preimage.extend_from_slice(&input.previous_output.to_bytes());
+        preimage.extend_from_slice(&script_code.to_bytes());
+        preimage.extend_from_slice(&value.to_le_bytes());
+        preimage.extend_from_slice(&input.sequence.to_le_bytes());
+        preimage.extend_from_slice(&hash_outputs);
+        preimage.extend_from_slice(&tx.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&sighash_type.to_u32().to_le_bytes());
+
+        Ok(double_sha256(&preimage))
+    }
+}
+
+impl fmt::Display for BitcoinTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        TransactionDisplay {
+            tx: self,
+            prevouts: None,
+        }
+        .fmt(f)
+    }
+}
+
+/// The `Display` layout, optionally with what each input spends.
+struct TransactionDisplay<'a> {
+    tx: &'a BitcoinTransaction,
+    prevouts: Option<&'a [TransactionOutput]>,
+}
+
+impl fmt::Display for TransactionDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tx = self.tx;
+        writeln!(f, "Transaction Version: {}", tx.version)?;
+        writeln!(f, "Input Count: {}", tx.inputs.len())?;
+
+        for (i, input) in tx.inputs.iter().enumerate() {
+            writeln!(f, "Input {}:", i)?;
+            writeln!(
+                f,
+                "  Previous Output: {}",
+                hex::encode(input.previous_output.txid.0)
+            )?;
+            writeln!(f, "  Previous Output Vout: {}", input.previous_output.vout)?;
+            writeln!(
+                f,
+                "  ScriptSig Length: {} bytes",
+                input.script_sig.bytes.len()
+            )?;
+            writeln!(f, "  ScriptSig: {}", hex::encode(&input.script_sig.bytes))?;
+            writeln!(f, "  Sequence: {}", input.sequence)?;
+
+            if let Some(prevouts) = self.prevouts {
+                let prevout = prevouts.get(i);
+                let value = prevout.map(|prevout| prevout.amount().to_string());
+                let address = prevout.and_then(|prevout| prevout.address(Network::Mainnet));
+                let unknown = || String::from("<unknown>");
+                writeln!(f, "  Spent Value: {}", value.unwrap_or_else(unknown))?;
+                writeln!(f, "  Spent Address: {}", address.unwrap_or_else(unknown))?;
+            }
+        }
+
+        writeln!(f, "Output Count: {}", tx.outputs.len())?;
+        for (i, output) in tx.outputs.iter().enumerate() {
+            writeln!(f, "Output {}: {}", i, output)?;
+        }
+
+        write!(f, "Lock Time: {}", tx.lock_time)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    version: u32,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    lock_time: u32,
+}
+
+impl TransactionBuilder {
+    /// Starts a version 2 transaction with a lock time of 0.
+    pub fn new() -> Self {
+        Self {
+            version: 2,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            lock_time: 0,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn add_input(
+        mut self,
+        previous_output: OutPoint,
+        script_sig: Script,
+        sequence: u32,
+    ) -> Self {
+        self.inputs
+            .push(TransactionInput::new(previous_output, script_sig, sequence));
+        self
+    }
+
+    pub fn add_output(mut self, value: u64, script_pubkey: Script) -> Self {
+        self.outputs
+            .push(TransactionOutput::new(value, script_pubkey));
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    pub fn build(self) -> BitcoinTransaction {
+        BitcoinTransaction::new(self.version, self.inputs, self.outputs, self.lock_time)
+    }
+}
 
-        write!(f, "Lock Time: {}", self.lock_time)
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
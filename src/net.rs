@@ -0,0 +1,143 @@
+//! P2P message payloads, dispatched on the command name from the message
+//! header.
+//!
+//! ```
+//! use rust_week_3_exercises::net::{decode_message, Message};
+//!
+//! let ping = decode_message("ping", &7u64.to_le_bytes()).unwrap();
+//! assert_eq!(ping, Message::Ping(7));
+//! ```
+
+use crate::{
+    BitcoinError, BitcoinTransaction, ByteReader, ByteSink, ByteSource, Decodable, Encodable, Txid,
+    decode_vec, encode_to_vec, encode_vec,
+};
+#[cfg(feature = "std")]
+use crate::{IoSource, encode_to_writer};
+use alloc::vec::Vec;
+
+pub const MSG_TX: u32 = 1;
+pub const MSG_BLOCK: u32 = 2;
+/// `MSG_TX` with the witness flag (bit 30), asking for witness data.
+pub const MSG_WITNESS_TX: u32 = 0x4000_0001;
+
+/// Most entries an `inv` message may carry.
+pub const MAX_INV_SIZE: usize = 50_000;
+
+/// An `inv`/`getdata` entry: a 4-byte type and a 32-byte hash in internal
+/// byte order, the same order as `Txid`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Inventory {
+    pub inv_type: u32,
+    pub hash: [u8; 32],
+}
+
+impl Inventory {
+    pub fn new(inv_type: u32, hash: [u8; 32]) -> Self {
+        Self { inv_type, hash }
+    }
+
+    pub fn tx(txid: Txid) -> Self {
+        Self::new(MSG_TX, txid.0)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|sink| self.write_to(sink))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<usize> {
+        encode_to_writer(writer, |sink| self.write_to(sink))
+    }
+
+    pub(crate) fn write_to<S: ByteSink>(&self, sink: &mut S) -> Result<(), S::Error> {
+        sink.put(&self.inv_type.to_le_bytes())?;
+        sink.put(&self.hash)
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, BitcoinError> {
+        Self::decode_from(reader)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: std::io::Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::decode_from(&mut IoSource::new(reader))
+    }
+
+    pub(crate) fn decode_from<S: ByteSource>(source: &mut S) -> Result<Self, BitcoinError> {
+        let inv_type = source.read_u32_le()?;
+        let hash = source.read_array()?;
+        Ok(Self { inv_type, hash })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        <Self as Decodable>::from_bytes(bytes)
+    }
+}
+
+impl Encodable for Inventory {
+    fn to_bytes(&self) -> Vec<u8> {
+        Inventory::to_bytes(self)
+    }
+}
+
+impl Decodable for Inventory {
+    const MIN_ENCODED_SIZE: usize = 36;
+
+    fn decode(reader: &mut ByteReader<'_>) -> Result<Self, BitcoinError> {
+        Inventory::decode(reader)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Message {
+    /// A serialized transaction.
+    Tx(BitcoinTransaction),
+    /// Objects the peer announces.
+    Inv(Vec<Inventory>),
+    /// The nonce to echo back in a `pong`.
+    Ping(u64),
+}
+
+impl Message {
+    /// The header command name for this message.
+    pub fn command(&self) -> &'static str {
+        match self {
+            Message::Tx(_) => "tx",
+            Message::Inv(_) => "inv",
+            Message::Ping(_) => "ping",
+        }
+    }
+
+    pub fn payload(&self) -> Vec<u8> {
+        match self {
+            Message::Tx(tx) => tx.to_bytes(),
+            Message::Inv(inventory) => encode_vec(inventory),
+            Message::Ping(nonce) => nonce.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// Decodes the payload of a `command` message. The payload must be used
+/// up exactly; unsupported commands are `InvalidFormat`.
+pub fn decode_message(command: &str, payload: &[u8]) -> Result<Message, BitcoinError> {
+    match command {
+        "tx" => BitcoinTransaction::from_bytes_exact(payload).map(Message::Tx),
+        "inv" => {
+            let (inventory, consumed) = decode_vec(payload)?;
+            if consumed != payload.len() || inventory.len() > MAX_INV_SIZE {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            Ok(Message::Inv(inventory))
+        }
+        "ping" => {
+            let mut reader = ByteReader::new(payload);
+            let nonce = reader.read_u64_le()?;
+            if !reader.is_empty() {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            Ok(Message::Ping(nonce))
+        }
+        _ => Err(BitcoinError::InvalidFormat),
+    }
+}
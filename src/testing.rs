@@ -0,0 +1,46 @@
+//! Helpers for checking encoder/decoder pairs in tests.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::BitcoinError;
+
+/// Asserts that `decode` reproduces `value` from `encode(value)` and that
+/// the length accounting is exact: the decoder reports consuming every
+/// byte, stops at the same place when trailing bytes follow, and fails on
+/// every strict prefix of the encoding.
+///
+/// ```
+/// use rust_week_3_exercises::{Script, testing::assert_roundtrip};
+///
+/// assert_roundtrip(&Script::new(vec![0x51]), Script::to_bytes, Script::from_bytes);
+/// ```
+pub fn assert_roundtrip<T, E, D>(value: &T, encode: E, decode: D)
+where
+    T: PartialEq + Debug,
+    E: Fn(&T) -> Vec<u8>,
+    D: Fn(&[u8]) -> Result<(T, usize), BitcoinError>,
+{
+    let bytes = encode(value);
+    let (decoded, consumed) = decode(&bytes).expect("encoding does not decode");
+    assert_eq!(&decoded, value, "decoded value differs");
+    assert_eq!(
+        consumed,
+        bytes.len(),
+        "decoder did not consume the encoding"
+    );
+
+    let mut padded = bytes.clone();
+    padded.push(0x00);
+    let (_, consumed) = decode(&padded).expect("encoding with a trailing byte does not decode");
+    assert_eq!(consumed, bytes.len(), "decoder consumed the trailing byte");
+
+    for len in 0..bytes.len() {
+        assert!(
+            decode(&bytes[..len]).is_err(),
+            "prefix of {} of {} bytes decoded",
+            len,
+            bytes.len()
+        );
+    }
+}
@@ -31,6 +31,111 @@ mod tests {
             let (decoded, consumed) = CompactSize::from_bytes(&bytes).unwrap();
             assert_eq!(decoded.value, value);
             assert_eq!(consumed, bytes.len());
+            assert_eq!(CompactSize::encoded_len(value), bytes.len());
+        }
+
+        const MAX_LEN: usize = CompactSize::encoded_len(u64::MAX);
+        assert_eq!(MAX_LEN, 9);
+    }
+
+    #[test]
+    fn test_compact_size_width_boundaries() {
+        let boundaries = [
+            (0xFC, 1),
+            (0xFD, 3),
+            (0xFFFF, 3),
+            (0x1_0000, 5),
+            (0xFFFF_FFFF, 5),
+            (0x1_0000_0000, 9),
+        ];
+        for (value, width) in boundaries {
+            let bytes = CompactSize::new(value).to_bytes();
+            assert_eq!(bytes.len(), width, "value {:#x}", value);
+            assert_eq!(CompactSize::encoded_len(value), width);
+            assert_eq!(
+                CompactSize::from_bytes_canonical(&bytes).unwrap().0.value,
+                value
+            );
+        }
+
+        assert_eq!(
+            CompactSize::try_new(MAX_COMPACT_SIZE),
+            Ok(CompactSize::new(MAX_COMPACT_SIZE))
+        );
+        assert_eq!(
+            CompactSize::try_new(MAX_COMPACT_SIZE + 1),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_canonical() {
+        let non_canonical = vec![
+            vec![0xFD, 0x10, 0x00],
+            vec![0xFD, 0xFC, 0x00],
+            vec![0xFE, 0xFF, 0xFF, 0x00, 0x00],
+            vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00],
+        ];
+        for bytes in non_canonical {
+            assert!(CompactSize::from_bytes(&bytes).is_ok());
+            assert_eq!(
+                CompactSize::from_bytes_canonical(&bytes),
+                Err(BitcoinError::InvalidFormat)
+            );
+            let (_, consumed, canonical) = CompactSize::from_bytes_checked(&bytes).unwrap();
+            assert_eq!((consumed, canonical), (bytes.len(), false));
+        }
+
+        let (cs, consumed) = CompactSize::from_bytes_canonical(&[0xFD, 0xFD, 0x00]).unwrap();
+        assert_eq!((cs.value, consumed), (0xFD, 3));
+        assert_eq!(
+            CompactSize::from_bytes_checked(&[0xFD, 0x10, 0x00]),
+            Ok((CompactSize::new(0x10), 3, false))
+        );
+        assert_eq!(
+            CompactSize::from_bytes_checked(&[0xFD, 0xFD, 0x00]),
+            Ok((CompactSize::new(0xFD), 3, true))
+        );
+        assert_eq!(
+            CompactSize::from_bytes_checked(&[0xFE, 0x00]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_decode_sequence() {
+        let bytes = [0x01, 0xFD, 0x00, 0x01, 0xFC];
+        let (values, consumed) = CompactSize::decode_sequence(&bytes, 3).unwrap();
+        assert_eq!(values, vec![1, 256, 252]);
+        assert_eq!(consumed, 5);
+
+        assert_eq!(
+            CompactSize::decode_sequence(&bytes, 0).unwrap(),
+            (vec![], 0)
+        );
+        assert_eq!(
+            CompactSize::decode_sequence(&bytes[..2], 2),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            CompactSize::decode_sequence(&bytes, 4),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_conversions() {
+        let cs = CompactSize::from(300u64);
+        assert_eq!(cs, CompactSize::new(300));
+        assert_eq!(u64::from(cs.clone()), 300);
+        assert_eq!(usize::try_from(cs).unwrap(), 300);
+        assert_eq!(CompactSize::try_from(7usize).unwrap().value, 7);
+
+        let max = CompactSize::new(u64::MAX);
+        if usize::BITS < 64 {
+            assert_eq!(usize::try_from(max), Err(BitcoinError::InvalidFormat));
+        } else {
+            assert_eq!(usize::try_from(max).unwrap(), usize::MAX);
         }
     }
 
@@ -45,6 +150,166 @@ mod tests {
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_txid_rpc_string() {
+        let txid = Txid(dummy_txid(0xCC));
+        let rpc = txid.to_rpc_string();
+        assert!(rpc.starts_with("cc00"));
+        assert_eq!(txid.to_string(), rpc);
+        assert_eq!(Txid::from_rpc_string(&rpc).unwrap(), txid);
+        assert_eq!(rpc.parse::<Txid>().unwrap(), txid);
+
+        assert_eq!("zz".parse::<Txid>(), Err(BitcoinError::InvalidFormat));
+        assert_eq!(
+            Txid::from_rpc_string(&rpc[..62]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_txid_from_slice() {
+        let bytes = dummy_txid(9);
+        let txid = Txid::from_slice(&bytes).unwrap();
+        assert_eq!(txid.as_bytes(), &bytes);
+        assert_eq!(
+            Txid::from_slice(&bytes[..31]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Txid::from_slice(&[0u8; 33]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_outpoint_vout_is_little_endian() {
+        let outpoint = OutPoint::new(dummy_txid(3), 0x01020304);
+        let bytes = outpoint.to_bytes();
+        assert_eq!(&bytes[..32], &dummy_txid(3));
+        assert_eq!(&bytes[32..], &[0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(OutPoint::from_bytes(&bytes), Ok((outpoint.clone(), 36)));
+
+        // Same layout inside a transaction input
+        let input = TransactionInput::new(outpoint, Script::new(vec![]), 0);
+        assert_eq!(&input.to_bytes()[32..36], &[0x04, 0x03, 0x02, 0x01]);
+    }
+
+    #[test]
+    fn test_outpoint_as_map_key() {
+        use std::collections::{BTreeMap, HashSet};
+
+        let mut utxos = BTreeMap::new();
+        utxos.insert(OutPoint::new(dummy_txid(2), 0), 30u64);
+        utxos.insert(OutPoint::new(dummy_txid(1), 5), 20);
+        utxos.insert(OutPoint::new(dummy_txid(1), 1), 10);
+        let values: Vec<u64> = utxos.values().copied().collect();
+        assert_eq!(values, vec![10, 20, 30]);
+
+        assert!(Txid([0x00; 32]) < Txid([0x01; 32]));
+        let set: HashSet<OutPoint> = utxos.keys().cloned().collect();
+        assert!(set.contains(&OutPoint::new(dummy_txid(1), 5)));
+    }
+
+    #[test]
+    fn test_txid_big_endian_hex() {
+        let display = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let txid = Txid::from_hex_be(display).unwrap();
+        assert_eq!(txid.0[0], 0x3b);
+        assert_eq!(txid.0[31], 0x4a);
+        assert_eq!(txid.to_hex_be(), display);
+        assert_eq!(
+            BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX)
+                .unwrap()
+                .txid(),
+            txid
+        );
+        assert_eq!(Txid::from_hex_be("4a5e"), Err(BitcoinError::InvalidFormat));
+    }
+
+    fn generic_roundtrip<T: Encodable + Decodable + PartialEq + core::fmt::Debug>(value: T) {
+        let bytes = Encodable::to_bytes(&value);
+        let (decoded, consumed) = <T as Decodable>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_encodable_decodable_traits() {
+        generic_roundtrip(CompactSize::new(0x1234));
+        generic_roundtrip(OutPoint::new(dummy_txid(7), 3));
+        generic_roundtrip(Script::new(vec![0x51, 0x52]));
+        generic_roundtrip(TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0xAA]),
+            0xFFFFFFFE,
+        ));
+        generic_roundtrip(TransactionOutput::new(5_000, Script::new(vec![0x6a])));
+        generic_roundtrip(BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap());
+    }
+
+    #[test]
+    fn test_encode_and_decode_vec() {
+        let outputs = vec![
+            TransactionOutput::new(1, Script::new(vec![0x51])),
+            TransactionOutput::new(2, Script::new(vec![])),
+        ];
+        let bytes = encode_vec(&outputs);
+        assert_eq!(bytes[0], 2);
+        assert_eq!(
+            decode_vec::<TransactionOutput>(&bytes),
+            Ok((outputs, bytes.len()))
+        );
+        assert_eq!(
+            decode_vec::<TransactionOutput>(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        // A count the remaining bytes cannot hold is rejected before parsing
+        let forged = [0xFE, 0xFF, 0xFF, 0xFF, 0x00, 0x00];
+        assert_eq!(
+            decode_vec::<OutPoint>(&forged),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(decode_vec::<Script>(&[0x00]), Ok((vec![], 1)));
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(CompactSize::default(), CompactSize::new(0));
+        assert!(Script::default().is_empty());
+        assert!(OutPoint::default().is_null());
+
+        let input = TransactionInput::default();
+        assert_eq!(input.previous_output, OutPoint::null());
+        assert!(input.script_sig.is_empty());
+        assert_eq!(input.sequence, 0xFFFFFFFF);
+
+        let tx = BitcoinTransaction {
+            lock_time: 100,
+            ..Default::default()
+        };
+        assert_eq!(tx, BitcoinTransaction::new(2, vec![], vec![], 100));
+    }
+
+    #[test]
+    fn test_outpoint_display_and_parse() {
+        let outpoint = OutPoint::new(dummy_txid(0xAB), 7);
+        let s = outpoint.to_string();
+        assert_eq!(
+            s,
+            "ab00000000000000000000000000000000000000000000000000000000000000:7"
+        );
+        assert_eq!(s.parse::<OutPoint>(), Ok(outpoint));
+
+        for bad in [
+            "ab00000000000000000000000000000000000000000000000000000000000000",
+            "zz:7",
+            "ab00000000000000000000000000000000000000000000000000000000000000:-1",
+        ] {
+            assert_eq!(bad.parse::<OutPoint>(), Err(BitcoinError::InvalidFormat));
+        }
+    }
+
     #[test]
     fn test_script_roundtrip() {
         let script_data = vec![0x76, 0xA9, 0x14, 0x88, 0xAC];
@@ -55,6 +320,368 @@ mod tests {
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_script_hex() {
+        let bare = "76a914111111111111111111111111111111111111111188ac";
+        let script = Script::from_hex(bare).unwrap();
+        assert_eq!(script.len(), 25);
+        assert_eq!(script.script_type(), ScriptType::P2PKH);
+        assert_eq!(script.to_hex(), bare);
+        assert_eq!(hex::encode(script.to_bytes()), format!("19{}", bare));
+        assert_eq!(Script::from_hex(""), Ok(Script::default()));
+        assert_eq!(Script::from_hex("7"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_witness_roundtrip() {
+        let mut witness = Witness::new();
+        assert!(witness.is_empty());
+        witness.push(vec![0xAB; 3]);
+        witness.push(vec![]);
+        assert_eq!(witness.len(), 2);
+        assert_eq!(witness[0], vec![0xAB; 3]);
+
+        let bytes = witness.to_bytes();
+        assert_eq!(bytes, vec![0x02, 0x03, 0xAB, 0xAB, 0xAB, 0x00]);
+        assert_eq!(witness.serialized_len(), bytes.len());
+        assert_eq!(Witness::from_bytes(&bytes), Ok((witness, bytes.len())));
+        assert_eq!(
+            Witness::from_bytes(&[0x01, 0x02, 0xAB]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_taproot_spend_type() {
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[3].1).unwrap();
+        let key_path = &tx.inputs[0].witness;
+        assert_eq!(key_path.taproot_spend_type(), Some(TaprootSpend::KeyPath));
+
+        let mut with_annex = key_path.clone();
+        with_annex.push(vec![0x50, 0x01]);
+        assert_eq!(with_annex.taproot_annex(), Some(&[0x50, 0x01][..]));
+        assert_eq!(with_annex.taproot_spend_type(), Some(TaprootSpend::KeyPath));
+
+        let mut control = vec![0xC1];
+        control.extend_from_slice(&[0x11; 32 + 32]);
+        let script_path = Witness::from(vec![vec![0xAB; 64], vec![0x51], control]);
+        assert_eq!(script_path.taproot_annex(), None);
+        assert_eq!(
+            script_path.taproot_spend_type(),
+            Some(TaprootSpend::ScriptPath)
+        );
+
+        // P2WPKH signature and pubkey
+        let p2wpkh = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        assert_eq!(p2wpkh.inputs[0].witness.taproot_spend_type(), None);
+        assert_eq!(Witness::new().taproot_spend_type(), None);
+    }
+
+    #[test]
+    fn test_oversized_script_length_does_not_panic() {
+        let mut bytes = vec![0xFF; 9];
+        bytes.extend_from_slice(&[0xAA; 4]);
+        assert_eq!(Script::from_bytes(&bytes), Err(BitcoinError::InvalidFormat));
+
+        let mut input = OutPoint::new(dummy_txid(1), 0).to_bytes();
+        input.extend_from_slice(&bytes);
+        assert_eq!(
+            TransactionInput::from_bytes(&input),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_byte_reader() {
+        let bytes = [0xFD, 0x03, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05];
+        let mut reader = ByteReader::new(&bytes);
+        assert_eq!(reader.read_compact_size().unwrap().value, 3);
+        assert_eq!(reader.position(), 3);
+        assert_eq!(reader.read_u32_le().unwrap(), 0x04030201);
+        assert_eq!(reader.remaining(), 1);
+
+        assert_eq!(reader.read_u32_le(), Err(BitcoinError::InsufficientBytes));
+        assert_eq!(reader.position(), 7);
+        assert_eq!(reader.read_u8().unwrap(), 0x05);
+        assert!(reader.is_empty());
+
+        let script_bytes = Script::new(vec![0xAA, 0xBB]).to_bytes();
+        let mut reader = ByteReader::new(&script_bytes[..2]);
+        assert_eq!(reader.read_script(), Err(BitcoinError::InsufficientBytes));
+        assert_eq!(reader.position(), 0);
+    }
+
+    #[test]
+    fn test_script_disassemble() {
+        let mut p2pkh = vec![0x76, 0xA9, 0x14];
+        p2pkh.extend_from_slice(&[0x11; 20]);
+        p2pkh.extend_from_slice(&[0x88, 0xAC]);
+        assert_eq!(
+            Script::new(p2pkh).disassemble().unwrap(),
+            vec![
+                "OP_DUP",
+                "OP_HASH160",
+                "<20 bytes>",
+                "OP_EQUALVERIFY",
+                "OP_CHECKSIG"
+            ]
+        );
+
+        let mut pushdata = vec![0x00, 0x4C, 0x03, 0x01, 0x02, 0x03, 0x4D, 0x01, 0x00, 0xFF];
+        pushdata.extend_from_slice(&[0x4E, 0x00, 0x00, 0x00, 0x00, 0x51, 0xBB]);
+        assert_eq!(
+            Script::new(pushdata).disassemble().unwrap(),
+            vec![
+                "OP_0",
+                "<3 bytes>",
+                "<1 bytes>",
+                "OP_0",
+                "OP_1",
+                "OP_UNKNOWN(0xbb)"
+            ]
+        );
+
+        for truncated in [vec![0x14, 0x00], vec![0x4C], vec![0x4D, 0x05, 0x00, 0x01]] {
+            assert_eq!(
+                Script::new(truncated).disassemble(),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    fn script_from_parts(parts: &[&[u8]]) -> Script {
+        Script::new(parts.concat())
+    }
+
+    #[test]
+    fn test_script_asm() {
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[0].1).unwrap();
+        let script_sig = &tx.inputs[0].script_sig;
+        let pushes = script_sig.parse_pushes().unwrap();
+        let (sig, pubkey) = (hex::encode(&pushes[0]), hex::encode(&pushes[1]));
+        assert!(sig.ends_with("01"));
+        assert_eq!(
+            script_sig.to_asm(true),
+            format!("{}[ALL] {}", &sig[..sig.len() - 2], pubkey)
+        );
+        assert_eq!(script_sig.to_asm(false), format!("{} {}", sig, pubkey));
+
+        let script = Script::builder()
+            .push_int(0)
+            .push_int(-1)
+            .push_int(16)
+            .push_int(1000)
+            .push_slice(&[0x81])
+            .push_opcode(opcodes::OP_DUP)
+            .into_script();
+        assert_eq!(script.to_asm(false), "0 -1 16 1000 -1 OP_DUP");
+
+        let p2pkh = Script::from_hex("76a914111111111111111111111111111111111111111188ac").unwrap();
+        assert_eq!(
+            p2pkh.to_asm(false),
+            "OP_DUP OP_HASH160 1111111111111111111111111111111111111111 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+        assert_eq!(
+            Script::new(vec![0x76, 0x05, 0x01]).to_asm(false),
+            "OP_DUP [error]"
+        );
+
+        // Data after OP_RETURN is never read as a signature
+        let mut op_return = vec![0x6A, pushes[0].len() as u8];
+        op_return.extend_from_slice(&pushes[0]);
+        assert_eq!(
+            Script::new(op_return).to_asm(true),
+            format!("OP_RETURN {}", sig)
+        );
+    }
+
+    #[test]
+    fn test_script_ref_view() {
+        let script =
+            Script::new(hex::decode("76a914111111111111111111111111111111111111111188ac").unwrap());
+        let mut buf = script.to_bytes();
+        buf.push(0xFF);
+
+        let mut reader = ByteReader::new(&buf);
+        let view = reader.read_script_ref().unwrap();
+        assert_eq!(reader.remaining(), 1);
+        assert_eq!(view.as_bytes(), script.bytes.as_slice());
+        assert_eq!(view, script.as_ref_view());
+        assert_eq!(view.script_type(), ScriptType::P2PKH);
+        assert_eq!(view.disassemble(), script.disassemble());
+        assert_eq!(view.to_bytes(), script.to_bytes());
+        assert_eq!(view.to_script(), script);
+    }
+
+    #[test]
+    fn test_script_type() {
+        let cases = vec![
+            (
+                script_from_parts(&[&[0x76, 0xA9, 0x14], &[0x11; 20], &[0x88, 0xAC]]),
+                ScriptType::P2PKH,
+            ),
+            (
+                script_from_parts(&[&[0xA9, 0x14], &[0x22; 20], &[0x87]]),
+                ScriptType::P2SH,
+            ),
+            (
+                script_from_parts(&[&[0x00, 0x14], &[0x33; 20]]),
+                ScriptType::P2WPKH,
+            ),
+            (
+                script_from_parts(&[&[0x00, 0x20], &[0x44; 32]]),
+                ScriptType::P2WSH,
+            ),
+            (
+                script_from_parts(&[&[0x51, 0x20], &[0x55; 32]]),
+                ScriptType::P2TR,
+            ),
+            (
+                Script::new(vec![0x6A, 0x02, 0xCA, 0xFE]),
+                ScriptType::OpReturn,
+            ),
+            // P2SH template with a 19-byte hash
+            (
+                script_from_parts(&[&[0xA9, 0x14], &[0x22; 19], &[0x87]]),
+                ScriptType::NonStandard,
+            ),
+            (
+                script_from_parts(&[&[0x00, 0x14], &[0x33; 21]]),
+                ScriptType::NonStandard,
+            ),
+            (Script::new(vec![]), ScriptType::NonStandard),
+        ];
+
+        for (script, expected) in cases {
+            assert_eq!(script.script_type(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_pushes() {
+        let signature = vec![0x30; 71];
+        let pubkey = vec![0x02; 33];
+        let script_sig = Script::builder()
+            .push_slice(&signature)
+            .push_slice(&pubkey)
+            .into_script();
+        assert_eq!(script_sig.parse_pushes().unwrap(), vec![signature, pubkey]);
+
+        let mixed = Script::new(vec![0x00, 0x51, 0x4C, 0x02, 0xAA, 0xBB, 0xAC]);
+        assert_eq!(
+            mixed.parse_pushes().unwrap(),
+            vec![vec![], vec![0xAA, 0xBB]]
+        );
+
+        assert_eq!(
+            Script::new(vec![0x47, 0x30]).parse_pushes(),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_parse_multisig() {
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[1].1).unwrap();
+        let pushes = tx.inputs[0].script_sig.parse_pushes().unwrap();
+        let redeem_script = Script::new(pushes.last().unwrap().clone());
+        let (m, pubkeys) = redeem_script.parse_multisig().unwrap();
+        assert_eq!((m, pubkeys.len()), (2, 3));
+        assert!(pubkeys.iter().all(|key| key.len() == 33));
+
+        let multisig = |m: i64, keys: &[&[u8]], n: i64| {
+            let mut builder = Script::builder().push_int(m);
+            for key in keys {
+                builder = builder.push_slice(key);
+            }
+            builder
+                .push_int(n)
+                .push_opcode(opcodes::OP_CHECKMULTISIG)
+                .into_script()
+        };
+        let (compressed, uncompressed) = ([0x02; 33], [0x04; 65]);
+        assert_eq!(
+            multisig(1, &[&compressed, &uncompressed], 2).parse_multisig(),
+            Some((1, vec![compressed.to_vec(), uncompressed.to_vec()]))
+        );
+        // n disagrees with the key count, m > n, and a 32-byte key
+        assert_eq!(multisig(1, &[&compressed], 2).parse_multisig(), None);
+        assert_eq!(multisig(2, &[&compressed], 1).parse_multisig(), None);
+        assert_eq!(multisig(1, &[&[0x02; 32]], 1).parse_multisig(), None);
+        assert_eq!(multisig(0, &[&compressed], 1).parse_multisig(), None);
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        let direct = Script::new(vec![0x6a, 0x03, 0xAA, 0xBB, 0xCC]);
+        assert_eq!(direct.op_return_data(), Some(&[0xAA, 0xBB, 0xCC][..]));
+
+        let mut pushdata1 = vec![0x6a, 0x4c, 80];
+        pushdata1.extend_from_slice(&[0x11; 80]);
+        assert_eq!(
+            Script::new(pushdata1).op_return_data(),
+            Some(&[0x11; 80][..])
+        );
+
+        for script in [
+            vec![0x6a],
+            vec![0x6a, 0x01, 0xAA, 0x01, 0xBB],
+            vec![0x6a, 0x05, 0xAA],
+            vec![0x6a, 0x51],
+            vec![0x51, 0x01, 0xAA],
+        ] {
+            assert_eq!(Script::new(script).op_return_data(), None);
+        }
+    }
+
+    #[test]
+    fn test_script_builder() {
+        let script = Script::builder()
+            .push_opcode(opcodes::OP_DUP)
+            .push_opcode(opcodes::OP_HASH160)
+            .push_slice(&[0x11; 20])
+            .push_opcode(opcodes::OP_EQUALVERIFY)
+            .push_opcode(opcodes::OP_CHECKSIG)
+            .into_script();
+        assert_eq!(script.script_type(), ScriptType::P2PKH);
+
+        let pushes = [
+            (75, vec![0x4B]),
+            (76, vec![0x4C, 0x4C]),
+            (255, vec![0x4C, 0xFF]),
+        ];
+        for (len, prefix) in pushes {
+            let script = Script::builder().push_slice(&vec![0xAA; len]).into_script();
+            assert_eq!(&script[..prefix.len()], prefix.as_slice());
+            assert_eq!(script.len(), prefix.len() + len);
+        }
+        let script = Script::builder().push_slice(&[0xAA; 256]).into_script();
+        assert_eq!(&script[..3], &[0x4D, 0x00, 0x01]);
+        let script = Script::builder().push_slice(&[0xAA; 0x10000]).into_script();
+        assert_eq!(&script[..5], &[0x4E, 0x00, 0x00, 0x01, 0x00]);
+
+        let ints = [
+            (0, vec![0x00]),
+            (-1, vec![0x4F]),
+            (1, vec![0x51]),
+            (16, vec![0x60]),
+            (17, vec![0x01, 0x11]),
+            (-2, vec![0x01, 0x82]),
+            (127, vec![0x01, 0x7F]),
+            (128, vec![0x02, 0x80, 0x00]),
+            (-128, vec![0x02, 0x80, 0x80]),
+            (255, vec![0x02, 0xFF, 0x00]),
+            (256, vec![0x02, 0x00, 0x01]),
+        ];
+        for (n, expected) in ints {
+            assert_eq!(
+                Script::builder().push_int(n).into_script().bytes,
+                expected,
+                "{}",
+                n
+            );
+        }
+    }
+
     #[test]
     fn test_tx_input_roundtrip() {
         let outpoint = OutPoint::new(dummy_txid(1), 0);
@@ -67,47 +694,2057 @@ mod tests {
     }
 
     #[test]
-    fn test_bitcoin_tx_roundtrip() {
-        let inputs = vec![TransactionInput::new(
-            OutPoint::new(dummy_txid(1), 0),
-            Script::new(vec![0x01, 0x02]),
-            0xFFFFFFFF,
-        )];
-        let tx = BitcoinTransaction::new(2, inputs.clone(), 1000);
-        let bytes = tx.to_bytes();
-        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
-        assert_eq!(parsed, tx);
+    fn test_sequence_semantics() {
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                sequence,
+            )
+        };
+
+        assert!(input(0xFFFFFFFF).is_final());
+        assert!(!input(0xFFFFFFFF).enables_rbf());
+        assert!(!input(0xFFFFFFFE).enables_rbf());
+        assert!(input(0xFFFFFFFD).enables_rbf());
+
+        assert_eq!(input(0xFFFFFFFF).relative_locktime(), None);
+        assert_eq!(input(0x80000010).relative_locktime(), None);
+        assert_eq!(
+            input(144).relative_locktime(),
+            Some(RelativeLockTime::Blocks(144))
+        );
+        let time = input(SEQUENCE_LOCKTIME_TYPE_FLAG | 10)
+            .relative_locktime()
+            .unwrap();
+        assert_eq!(time, RelativeLockTime::Time(10));
+        assert_eq!(time.seconds(), Some(5120));
+        // Bits outside the type flag and value are ignored
+        assert_eq!(
+            input(0x00FF0005).relative_locktime(),
+            Some(RelativeLockTime::Time(5))
+        );
+    }
+
+    #[test]
+    fn test_tx_output_roundtrip() {
+        let script = Script::new(vec![0x76, 0xA9, 0x14, 0x88, 0xAC]);
+        let output = TransactionOutput::new(50_000, script);
+        let bytes = output.to_bytes();
+        assert_eq!(&bytes[0..8], &50_000u64.to_le_bytes());
+        let (parsed, consumed) = TransactionOutput::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, output);
         assert_eq!(consumed, bytes.len());
+
+        assert_eq!(
+            TransactionOutput::from_bytes(&bytes[0..7]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    fn output_with_script(script_hex: &str) -> TransactionOutput {
+        TransactionOutput::new(0, Script::new(hex::decode(script_hex).unwrap()))
     }
 
     #[test]
-    fn test_bitcoin_tx_json_serialization() {
-        let input = TransactionInput::new(
-            OutPoint::new(dummy_txid(0xAB), 3),
-            Script::new(vec![0xDE, 0xAD, 0xBE, 0xEF]),
-            0xABCDEF01,
+    fn test_amount() {
+        assert_eq!(Amount::from_sat(12_345).to_string(), "0.00012345 BTC");
+        assert_eq!(Amount(MAX_MONEY).to_string(), "21000000.00000000 BTC");
+        assert_eq!(Amount::from_btc(0.00012345), Ok(Amount(12_345)));
+        assert_eq!(Amount::from_btc(0.1), Ok(Amount(10_000_000)));
+        assert_eq!(Amount::from_btc(21_000_000.0), Ok(Amount(MAX_MONEY)));
+        assert_eq!(Amount(50 * COIN).to_btc(), 50.0);
+
+        for btc in [
+            21_000_000.00000001,
+            -1.0,
+            f64::NAN,
+            f64::INFINITY,
+            0.000000001,
+        ] {
+            assert_eq!(
+                Amount::from_btc(btc),
+                Err(BitcoinError::InvalidFormat),
+                "{}",
+                btc
+            );
+        }
+
+        let output = TransactionOutput::new(150_000_000, Script::new(vec![]));
+        assert_eq!(output.amount().to_string(), "1.50000000 BTC");
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        let mut p2pkh = output_with_script("76a914111111111111111111111111111111111111111188ac");
+        assert_eq!(p2pkh.dust_threshold(DUST_RELAY_TX_FEE), 546);
+        p2pkh.value = 545;
+        assert!(p2pkh.is_dust_default());
+        p2pkh.value = 546;
+        assert!(!p2pkh.is_dust_default());
+
+        let p2wpkh = output_with_script("0014751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_eq!(p2wpkh.dust_threshold(DUST_RELAY_TX_FEE), 294);
+        assert_eq!(p2wpkh.dust_threshold(1000), 98);
+
+        let op_return = output_with_script("6a0401020304");
+        assert!(!op_return.is_dust_default());
+    }
+
+    #[test]
+    fn test_decode_message() {
+        use rust_week_3_exercises::net::{Message, decode_message};
+
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        let message = decode_message("tx", &tx.to_bytes()).unwrap();
+        assert_eq!(message, Message::Tx(tx.clone()));
+        assert_eq!(message.command(), "tx");
+        assert_eq!(message.payload(), tx.to_bytes());
+
+        let mut trailing = tx.to_bytes();
+        trailing.push(0x00);
+        assert_eq!(
+            decode_message("tx", &trailing),
+            Err(BitcoinError::InvalidFormat)
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 999);
 
-        let json = serde_json::to_string_pretty(&tx).unwrap();
-        let parsed: BitcoinTransaction = serde_json::from_str(&json).unwrap();
-        assert_eq!(tx, parsed);
+        let ping = decode_message("ping", &[1, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(ping, Message::Ping(1));
+        assert_eq!(
+            decode_message("ping", &[1, 0, 0]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            decode_message("version", &[]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
 
-        assert!(json.contains("\"version\": 1"));
-        assert!(json.contains("\"lock_time\": 999"));
+    #[test]
+    fn test_inv_message() {
+        use rust_week_3_exercises::net::{Inventory, MSG_TX, Message, decode_message};
+
+        let txid = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX)
+            .unwrap()
+            .txid();
+        let item = Inventory::tx(txid.clone());
+        let bytes = item.to_bytes();
+        assert_eq!(bytes[..4], [0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(bytes[4..], txid.0);
+        assert_eq!(Inventory::from_bytes(&bytes), Ok((item, 36)));
+        #[cfg(feature = "std")]
+        {
+            let mut stream = Vec::new();
+            assert_eq!(item.consensus_encode(&mut stream).unwrap(), 36);
+            assert_eq!(stream, bytes);
+            assert_eq!(Inventory::consensus_decode(&mut &stream[..]), Ok(item));
+        }
+
+        let message = Message::Inv(vec![item, Inventory::new(MSG_TX, [0x11; 32])]);
+        let payload = message.payload();
+        assert_eq!(payload.len(), 1 + 2 * 36);
+        assert_eq!(payload[0], 0x02);
+        assert_eq!(decode_message("inv", &payload), Ok(message));
+
+        // Two entries no longer fit, so the count itself is rejected
+        assert_eq!(
+            decode_message("inv", &payload[..payload.len() - 1]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let mut trailing = payload.clone();
+        trailing.push(0x00);
+        assert_eq!(
+            decode_message("inv", &trailing),
+            Err(BitcoinError::InvalidFormat)
+        );
     }
 
     #[test]
-    fn test_bitcoin_transaction_display() {
-        let input = TransactionInput::new(
-            OutPoint::new(dummy_txid(0xCD), 7),
-            Script::new(vec![0x01, 0x02, 0x03]),
-            0xFFFFFFFF,
+    fn test_network_magic() {
+        assert_eq!(Network::Mainnet.magic(), [0xf9, 0xbe, 0xb4, 0xd9]);
+        for network in [
+            Network::Mainnet,
+            Network::Testnet,
+            Network::Regtest,
+            Network::Signet,
+        ] {
+            assert_eq!(Network::from_magic(&network.magic()), Some(network));
+        }
+        assert_eq!(Network::from_magic(&[0; 4]), None);
+    }
+
+    #[test]
+    fn test_base58_check() {
+        use rust_week_3_exercises::base58;
+
+        let payload = hex::decode("0062e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        assert_eq!(
+            base58::decode_check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap(),
+            payload
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 0);
-        let output = format!("{}", tx);
-        assert!(output.contains("Version: 1"));
-        assert!(output.contains("Lock Time: 0"));
-        assert!(output.contains("Previous Output Vout: 7"));
+        assert_eq!(
+            base58::decode_check(&base58::encode_check(&[0; 21])).unwrap(),
+            [0; 21]
+        );
+        assert_eq!(
+            base58::encode_check(&[0; 21]),
+            "1111111111111111111114oLvT2"
+        );
+
+        for bad in [
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb",
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfN0",
+            "1",
+            "",
+        ] {
+            assert_eq!(base58::decode_check(bad), Err(BitcoinError::InvalidFormat));
+        }
+    }
+
+    // Test vectors from BIP173 and BIP350
+    #[test]
+    fn test_bech32_decode() {
+        use rust_week_3_exercises::bech32;
+
+        assert_eq!(
+            bech32::decode("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"),
+            Ok((
+                "bc".to_string(),
+                0,
+                hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap()
+            ))
+        );
+        let taproot = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+        let (hrp, version, program) = bech32::decode(taproot).unwrap();
+        assert_eq!((hrp.as_str(), version), ("bc", 1));
+        assert_eq!(bech32::encode(&hrp, version, &program).unwrap(), taproot);
+
+        for bad in [
+            // Mixed case
+            "bc1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+            // Bech32 checksum on a v1 program, and bech32m on v0
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+            // Invalid character, missing separator, bad program length
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3tb",
+            "bcqw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "bc1rw5uspcuh",
+        ] {
+            assert_eq!(
+                bech32::decode(bad),
+                Err(BitcoinError::InvalidFormat),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_address() {
+        let cases = vec![
+            (
+                "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+                Network::Mainnet,
+                "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+            ),
+            (
+                "a914748284390f9e263a4b766a75d0633c50426eb87587",
+                Network::Mainnet,
+                "3CK4fEwbMP7heJarmU4eqA3sMbVJyEnU3V",
+            ),
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                Network::Mainnet,
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            ),
+            (
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                Network::Testnet,
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                Network::Mainnet,
+                "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            ),
+        ];
+        for (script_hex, network, address) in cases {
+            assert_eq!(
+                output_with_script(script_hex).address(network).as_deref(),
+                Some(address)
+            );
+        }
+
+        assert_eq!(
+            output_with_script("6a0401020304").address(Network::Mainnet),
+            None
+        );
+        // Version 0 programs must be 20 or 32 bytes
+        assert_eq!(
+            output_with_script("00100102030405060708090a0b0c0d0e0f10").address(Network::Mainnet),
+            None
+        );
+        assert_eq!(output_with_script("51").address(Network::Regtest), None);
+    }
+
+    #[test]
+    fn test_bitcoin_tx_roundtrip() {
+        let inputs = vec![TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0x01, 0x02]),
+            0xFFFFFFFF,
+        )];
+        let outputs = vec![TransactionOutput::new(
+            12_345,
+            Script::new(vec![0x00, 0x14, 0xAA]),
+        )];
+        let tx = BitcoinTransaction::new(2, inputs.clone(), outputs, 1000);
+        let bytes = tx.to_bytes();
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_bitcoin_tx_output_wire_layout() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let output = TransactionOutput::new(1, Script::new(vec![0x51]));
+        let tx = BitcoinTransaction::new(1, vec![input.clone()], vec![output.clone()], 7);
+        let bytes = tx.to_bytes();
+
+        let mut expected = vec![0x01, 0x00, 0x00, 0x00, 0x01];
+        expected.extend_from_slice(&input.to_bytes());
+        expected.push(0x01);
+        expected.extend_from_slice(&output.to_bytes());
+        expected.extend_from_slice(&[0x07, 0x00, 0x00, 0x00]);
+        assert_eq!(bytes, expected);
+
+        let truncated = &bytes[..bytes.len() - 2];
+        let err = BitcoinTransaction::from_bytes(truncated).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+        assert_eq!(err.offset(), Some(bytes.len() - 4));
+    }
+
+    #[test]
+    fn test_implausible_counts_rejected() {
+        // Version, then a claim of 0xFFFFFFFF inputs with almost no data
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x00, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF];
+        bytes.extend_from_slice(&[0x00; 64]);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        assert_eq!(err.offset(), Some(4));
+
+        // Two inputs need at least 82 bytes after the count
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x00, 0x02];
+        bytes.extend_from_slice(&input.to_bytes());
+        bytes.extend_from_slice(&[0x00; 40]);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+
+        let tx = BitcoinTransaction::new(1, vec![input], vec![], 0);
+        let mut bytes = tx.to_bytes();
+        let count_pos = bytes.len() - 5;
+        bytes[count_pos] = 0xFC;
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        assert_eq!(err.offset(), Some(count_pos));
+
+        // So is a scriptSig length longer than the rest of the buffer
+        let mut bytes = tx.to_bytes();
+        bytes[41] = 0xFC;
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        assert_eq!(err.offset(), Some(41));
+    }
+
+    #[test]
+    fn test_segwit_tx_roundtrip() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 1),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            ),
+        ];
+        let outputs = vec![TransactionOutput::new(1_000, Script::new(vec![0x00, 0x14]))];
+        let mut tx = BitcoinTransaction::new(2, inputs, outputs, 0);
+        tx.inputs[0].push_witness_item(vec![0x30; 71]);
+        tx.inputs[0].push_witness_item(vec![0x02; 33]);
+        assert!(tx.inputs[0].has_witness() && !tx.inputs[1].has_witness());
+        assert_eq!(tx.inputs[0].witness_items()[1], vec![0x02; 33]);
+
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_consensus_encode_matches_to_bytes() {
+        let mut tx = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(tx.consensus_encode(&mut out).unwrap(), out.len());
+        assert_eq!(out, tx.to_bytes());
+
+        tx.inputs[0].witness = vec![vec![0xAB; 3]].into();
+        let mut out = Vec::new();
+        assert_eq!(tx.consensus_encode(&mut out).unwrap(), tx.size());
+        assert_eq!(out, tx.to_bytes());
+
+        let mut out = Vec::new();
+        let input = &tx.inputs[0];
+        assert_eq!(input.consensus_encode(&mut out).unwrap(), out.len());
+        assert_eq!(out, input.to_bytes());
+
+        let mut out = std::io::Cursor::new([0u8; 4]);
+        assert!(tx.consensus_encode(&mut out).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_consensus_decode_from_stream() {
+        let mut segwit = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        segwit.inputs[0].witness = vec![vec![0xAB; 3]].into();
+        let legacy = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+
+        let mut bytes = segwit.to_bytes();
+        bytes.extend_from_slice(&legacy.to_bytes());
+        let mut stream = std::io::Cursor::new(bytes.clone());
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut stream).unwrap(),
+            segwit
+        );
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut stream).unwrap(),
+            legacy
+        );
+        assert_eq!(stream.position() as usize, bytes.len());
+
+        let mut input = std::io::Cursor::new(legacy.inputs[0].to_bytes());
+        assert_eq!(
+            TransactionInput::consensus_decode(&mut input).unwrap(),
+            legacy.inputs[0]
+        );
+
+        let mut bad_flag = legacy.to_bytes();
+        bad_flag.splice(4..4, [0x00, 0x02]);
+        let err = BitcoinTransaction::consensus_decode(&mut &bad_flag[..]).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+
+        let legacy_bytes = legacy.to_bytes();
+        let mut truncated = &legacy_bytes[..legacy_bytes.len() - 1];
+        let err = BitcoinTransaction::consensus_decode(&mut truncated).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+        assert_eq!(
+            CompactSize::consensus_decode(&mut &[0xFE, 0x01][..]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_error_display_and_conversion() {
+        let legacy_bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let parse = |bytes: &[u8]| -> Result<BitcoinTransaction, Box<dyn std::error::Error>> {
+            Ok(BitcoinTransaction::from_bytes(bytes)?.0)
+        };
+        let err = parse(&legacy_bytes[..legacy_bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected end of data at byte 200");
+        assert_eq!(err.source().unwrap().to_string(), "unexpected end of data");
+        assert_eq!(
+            BitcoinError::InvalidTransaction("bad-txns-vin-empty").to_string(),
+            "invalid transaction: bad-txns-vin-empty"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_error_conversion() {
+        struct Broken;
+        impl std::io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::PermissionDenied.into())
+            }
+        }
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut Broken).map_err(|e| e.kind().clone()),
+            Err(BitcoinError::Io(std::io::ErrorKind::PermissionDenied))
+        );
+        let eof = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(BitcoinError::from(eof), BitcoinError::InsufficientBytes);
+
+        // A failure while peeking for the segwit marker aborts decoding
+        // rather than reading as "no marker" and carrying on
+        struct FailsOnceAfterVersion {
+            bytes: Vec<u8>,
+            pos: usize,
+            failed: bool,
+        }
+        impl std::io::Read for FailsOnceAfterVersion {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.pos == 4 && !self.failed {
+                    self.failed = true;
+                    return Err(std::io::ErrorKind::ConnectionReset.into());
+                }
+                let n = buf.len().min(self.bytes.len() - self.pos);
+                buf[..n].copy_from_slice(&self.bytes[self.pos..self.pos + n]);
+                self.pos += n;
+                Ok(n)
+            }
+        }
+        let mut reader = FailsOnceAfterVersion {
+            bytes: hex::decode(GENESIS_COINBASE_HEX).unwrap(),
+            pos: 0,
+            failed: false,
+        };
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut reader).map_err(|e| e.kind().clone()),
+            Err(BitcoinError::Io(std::io::ErrorKind::ConnectionReset))
+        );
+    }
+
+    #[test]
+    fn test_zero_byte_after_version_is_segwit_marker() {
+        // Legacy encoding of a transaction with no inputs: 00 02 reads as a
+        // marker with an unknown flag rather than as an empty vin
+        let outputs = vec![
+            TransactionOutput::new(1, Script::new(vec![0x51])),
+            TransactionOutput::new(2, Script::new(vec![0x52])),
+        ];
+        let bytes = BitcoinTransaction::new(1, vec![], outputs, 0).to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x02]);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        assert_eq!(err.offset(), Some(5));
+
+        let empty = BitcoinTransaction::new(1, vec![], vec![], 0).to_bytes();
+        let err = BitcoinTransaction::from_bytes(&empty).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+
+        // With a 0x01 flag the same zero byte starts a SegWit transaction
+        let mut tx = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        tx.inputs[0].push_witness_item(vec![0x01]);
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        assert_eq!(BitcoinTransaction::from_bytes(&bytes).unwrap().0, tx);
+    }
+
+    #[test]
+    fn test_segwit_marker_without_witness_rejected() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let legacy = BitcoinTransaction::new(2, vec![input], vec![], 0).to_bytes();
+
+        let mut bytes = legacy[..4].to_vec();
+        bytes.extend_from_slice(&[0x00, 0x01]);
+        bytes.extend_from_slice(&legacy[4..legacy.len() - 4]);
+        bytes.push(0x00);
+        bytes.extend_from_slice(&legacy[legacy.len() - 4..]);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        assert_eq!(err.offset(), Some(4));
+    }
+
+    const GENESIS_COINBASE_HEX: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    #[test]
+    fn test_hashes() {
+        use rust_week_3_exercises::hashes::{double_sha256, hash160, sha256};
+
+        assert_eq!(
+            sha256(b""),
+            hex_array("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            hex_array("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            double_sha256(b""),
+            hex_array("5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456")
+        );
+
+        // The genesis output key hashes to 1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa
+        let genesis = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let pubkey = &genesis.outputs[0].script_pubkey[1..66];
+        assert_eq!(
+            hash160(pubkey).to_vec(),
+            hex::decode("62e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verify_p2pkh() {
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[0].1).unwrap();
+        let script_sig = &tx.inputs[0].script_sig;
+        let pushes = script_sig.parse_pushes().unwrap();
+        let (signature, pubkey) = (&pushes[0], &pushes[1]);
+        let hash = hashes::hash160(pubkey);
+        let script_pubkey = script_from_parts(&[&[0x76, 0xA9, 0x14], &hash, &[0x88, 0xAC]]);
+        let sighash = [0x42; 32];
+
+        let checked =
+            |sig: &[u8], key: &[u8]| sig == &signature[..signature.len() - 1] && key == &pubkey[..];
+        assert_eq!(
+            script_pubkey.verify_p2pkh(script_sig, &sighash, checked),
+            Ok(true)
+        );
+        assert_eq!(
+            script_pubkey.verify_p2pkh(script_sig, &sighash, |_, _| false),
+            Ok(false)
+        );
+
+        // A SIGHASH_NONE signature does not commit to the ALL digest
+        let mut none_signature = signature.clone();
+        *none_signature.last_mut().unwrap() = SIGHASH_NONE as u8;
+        let none_sig = ScriptBuilder::new()
+            .push_slice(&none_signature)
+            .push_slice(pubkey)
+            .into_script();
+        assert_eq!(
+            script_pubkey.verify_p2pkh(&none_sig, &sighash, |_, _| true),
+            Ok(false)
+        );
+
+        let other_key = script_from_parts(&[&[0x76, 0xA9, 0x14], &[0x11; 20], &[0x88, 0xAC]]);
+        assert_eq!(
+            other_key.verify_p2pkh(script_sig, &sighash, |_, _| true),
+            Ok(false)
+        );
+        assert_eq!(
+            script_pubkey.verify_p2pkh(&Script::new(vec![0x51]), &sighash, |_, _| true),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            script_sig.verify_p2pkh(script_sig, &sighash, |_, _| true),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_coinbase_height_and_subsidy() {
+        let coinbase = |script_sig: Vec<u8>| {
+            TransactionBuilder::new()
+                .add_input(OutPoint::null(), Script::new(script_sig), u32::MAX)
+                .build()
+        };
+        // Height 840,000 pushed as three bytes
+        let tx = coinbase(vec![0x03, 0x40, 0xD1, 0x0C, 0xAA]);
+        assert_eq!(tx.coinbase_height(), Some(840_000));
+        assert_eq!(coinbase(vec![0x52]).coinbase_height(), Some(2));
+        // Non-minimal and negative encodings
+        assert_eq!(coinbase(vec![0x02, 0x05, 0x00]).coinbase_height(), None);
+        assert_eq!(coinbase(vec![0x01, 0x85]).coinbase_height(), None);
+        assert_eq!(coinbase(vec![0x6A]).coinbase_height(), None);
+
+        let mut spend = tx.clone();
+        spend.inputs[0].previous_output = OutPoint::new(dummy_txid(1), 0);
+        assert_eq!(spend.coinbase_height(), None);
+
+        assert_eq!(block_subsidy(0), 50 * COIN);
+        assert_eq!(block_subsidy(209_999), 50 * COIN);
+        assert_eq!(block_subsidy(210_000), 25 * COIN);
+        assert_eq!(block_subsidy(840_000), 3 * COIN + 12_500_000);
+        assert_eq!(block_subsidy(64 * HALVING_INTERVAL), 0);
+    }
+
+    #[test]
+    fn test_txid_and_wtxid() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+
+        let mut expected =
+            hex::decode("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b")
+                .unwrap();
+        expected.reverse();
+        assert_eq!(tx.txid().0.to_vec(), expected);
+        assert_eq!(tx.wtxid(), tx.txid());
+
+        let mut segwit = tx.clone();
+        segwit.inputs[0].witness = vec![vec![0x00; 32]].into();
+        assert_eq!(segwit.txid(), tx.txid());
+        assert_ne!(segwit.wtxid(), tx.wtxid());
+    }
+
+    #[test]
+    fn test_strip_witness() {
+        let legacy = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let mut segwit = legacy.clone();
+        segwit.inputs[0].push_witness_item(vec![0xAB; 3]);
+
+        assert_eq!(segwit.to_bytes_legacy(), legacy.to_bytes());
+        assert_eq!(segwit.strip_witness(), legacy);
+        assert_eq!(segwit.strip_witness().to_bytes(), segwit.to_bytes_legacy());
+
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&segwit.to_bytes_legacy()).unwrap();
+        assert_eq!(parsed, segwit.strip_witness());
+        assert_eq!(consumed, legacy.size());
+    }
+
+    #[test]
+    fn test_input_script_builders() {
+        let tx = BitcoinTransaction::from_hex(LEGACY_SIGHASH_TX_HEX).unwrap();
+        let input = &tx.inputs[0];
+        let cleared = input.with_empty_script();
+        assert!(cleared.script_sig.is_empty());
+        assert_eq!(cleared.previous_output, input.previous_output);
+        assert_eq!(cleared.sequence, input.sequence);
+        assert_eq!(
+            input.with_script(Script::new(vec![0x51])).script_sig,
+            Script::new(vec![0x51])
+        );
+
+        let only_first = tx.map_inputs(|i, input| {
+            if i == 0 {
+                input.clone()
+            } else {
+                input.with_empty_script()
+            }
+        });
+        assert_eq!(only_first.inputs[0], tx.inputs[0]);
+        assert!(
+            only_first.inputs[1..]
+                .iter()
+                .all(|input| input.script_sig.is_empty())
+        );
+        assert_eq!(only_first.outputs, tx.outputs);
+    }
+
+    #[test]
+    fn test_eq_ignore_witness() {
+        let legacy = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let mut segwit = legacy.clone();
+        segwit.inputs[0].push_witness_item(vec![0xAB; 3]);
+        assert_ne!(segwit, legacy);
+        assert!(segwit.eq_ignore_witness(&legacy));
+        assert!(segwit.strip_witness().eq_ignore_witness(&segwit));
+
+        let mut changed = segwit.clone();
+        changed.inputs[0].sequence = 0;
+        assert!(!changed.eq_ignore_witness(&segwit));
+        let mut extra = segwit.clone();
+        extra.inputs.push(TransactionInput::default());
+        assert!(!extra.eq_ignore_witness(&segwit));
+        let mut relocked = segwit.clone();
+        relocked.lock_time = 1;
+        assert!(!relocked.eq_ignore_witness(&segwit));
+    }
+
+    #[test]
+    fn test_same_txid() {
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        let mut rewitnessed = tx.clone();
+        rewitnessed.inputs[0].witness = vec![vec![0x00; 71], vec![0x02; 33]].into();
+        assert_ne!(rewitnessed.wtxid(), tx.wtxid());
+        assert!(rewitnessed.same_txid(&tx));
+        assert!(tx.strip_witness().same_txid(&tx));
+
+        let mut respent = tx.clone();
+        respent.outputs[0].value -= 1;
+        assert!(!respent.same_txid(&tx));
+    }
+
+    #[test]
+    fn test_from_bytes_with_options() {
+        let tx = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let bytes = tx.to_bytes();
+        let lenient = ParseOptions::default();
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_options(&bytes, &lenient),
+            BitcoinTransaction::from_bytes(&bytes)
+        );
+
+        // Input count 1 written as FD 01 00
+        let mut padded = bytes[..4].to_vec();
+        padded.extend_from_slice(&[0xFD, 0x01, 0x00]);
+        padded.extend_from_slice(&bytes[5..]);
+        assert!(BitcoinTransaction::from_bytes_with_options(&padded, &lenient).is_ok());
+        let canonical = ParseOptions {
+            require_canonical_compact_size: true,
+            ..Default::default()
+        };
+        let err = BitcoinTransaction::from_bytes_with_options(&padded, &canonical).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&BitcoinError::InvalidFormat, Some(4))
+        );
+
+        let no_inputs = ParseOptions {
+            max_inputs: Some(0),
+            ..Default::default()
+        };
+        let err = BitcoinTransaction::from_bytes_with_options(&bytes, &no_inputs).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&BitcoinError::InvalidFormat, Some(4))
+        );
+        // The count alone decides, before any input is decoded
+        let err =
+            BitcoinTransaction::from_bytes_with_options(&bytes[..60], &no_inputs).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&BitcoinError::InvalidFormat, Some(4))
+        );
+
+        // The coinbase scriptSig is 77 bytes long
+        let short_scripts = ParseOptions {
+            max_script_len: Some(76),
+            ..Default::default()
+        };
+        let err = BitcoinTransaction::from_bytes_with_options(&bytes, &short_scripts).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&BitcoinError::InvalidFormat, Some(41))
+        );
+
+        let mut segwit = tx.clone();
+        segwit.inputs[0].push_witness_item(vec![0x01]);
+        let legacy_only = ParseOptions {
+            allow_witness: false,
+            ..Default::default()
+        };
+        assert!(BitcoinTransaction::from_bytes_with_options(&bytes, &legacy_only).is_ok());
+        let err = BitcoinTransaction::from_bytes_with_options(&segwit.to_bytes(), &legacy_only)
+            .unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&BitcoinError::InvalidFormat, Some(4))
+        );
+    }
+
+    #[test]
+    fn test_from_hex_lenient() {
+        let expected = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let (head, tail) = GENESIS_COINBASE_HEX.split_at(40);
+        let pasted = format!("  {}\n\t{} \r\n", head, tail);
+        assert_eq!(BitcoinTransaction::from_hex_lenient(&pasted), Ok(expected));
+        assert_eq!(
+            BitcoinTransaction::from_hex(&pasted),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // Odd length and trailing bytes are still rejected after stripping
+        let odd = format!("{} 0", GENESIS_COINBASE_HEX);
+        assert_eq!(
+            BitcoinTransaction::from_hex_lenient(&odd),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let trailing = format!("{} 00", GENESIS_COINBASE_HEX);
+        assert_eq!(
+            BitcoinTransaction::from_hex_lenient(&trailing),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_exact() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let tx = BitcoinTransaction::from_bytes_exact(&bytes).unwrap();
+        assert_eq!(tx.to_bytes(), bytes);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0x00);
+        assert!(BitcoinTransaction::from_bytes(&trailing).is_ok());
+        assert_eq!(
+            BitcoinTransaction::from_bytes_exact(&trailing),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_with_remainder() {
+        let genesis = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let mut bytes = genesis.repeat(2);
+        bytes.extend_from_slice(&[0xAA, 0xBB]);
+
+        let (first, rest) = BitcoinTransaction::from_bytes_with_remainder(&bytes).unwrap();
+        let (second, rest) = BitcoinTransaction::from_bytes_with_remainder(rest).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(rest, &[0xAA, 0xBB]);
+        let err = BitcoinTransaction::from_bytes_with_remainder(rest).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+    }
+
+    #[test]
+    fn test_annotated_hex() {
+        let mut tx = BitcoinTransaction::from_hex(MAINNET_TXS[0].1).unwrap();
+        let annotated = tx.annotated_hex();
+        let lines: Vec<&str> = annotated.lines().collect();
+        assert_eq!(lines[0], "0000  02000000  # version");
+        assert_eq!(lines[1], "0004  01  # input count");
+        assert!(lines[2].starts_with("0005  725aab4d") && lines[2].ends_with("# input 0 txid"));
+        assert_eq!(lines.last(), Some(&"00b8  00000000  # lock time"));
+
+        // The hex column concatenates to the plain serialization
+        let hex_column = |annotated: &str| -> String {
+            annotated
+                .lines()
+                .map(|line| line.split("  ").nth(1).unwrap())
+                .collect()
+        };
+        assert_eq!(hex_column(&annotated), tx.to_hex());
+
+        tx.inputs[0].push_witness_item(vec![]);
+        tx.inputs[0].push_witness_item(vec![0xAB; 2]);
+        let annotated = tx.annotated_hex();
+        assert!(annotated.contains("0004  0001  # segwit marker and flag\n"));
+        assert!(annotated.contains("  02  # input 0 witness item count\n"));
+        assert!(annotated.contains("  00  # input 0 witness 0 length\n"));
+        assert!(annotated.contains("  abab  # input 0 witness 1\n"));
+        assert_eq!(hex_column(&annotated), tx.to_hex());
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let first = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let second = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .add_output(1, Script::new(vec![0x51]))
+            .build();
+        let mut bytes = first.clone();
+        bytes.extend_from_slice(&second.to_bytes());
+
+        let (txs, consumed) = BitcoinTransaction::parse_many(&bytes, 2).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(txs[0].to_bytes(), first);
+        assert_eq!(txs[1], second);
+
+        let (txs, consumed) = BitcoinTransaction::parse_many(&bytes, 1).unwrap();
+        assert_eq!((txs.len(), consumed), (1, first.len()));
+
+        let err = BitcoinTransaction::parse_many(&bytes[..bytes.len() - 1], 2).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+        assert_eq!(err.offset(), Some(bytes.len() - 4));
+    }
+
+    #[test]
+    fn test_parse_block_transactions() {
+        let genesis = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let second = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        let mut bytes = vec![0x02];
+        bytes.extend_from_slice(&genesis.to_bytes());
+        bytes.extend_from_slice(&second.to_bytes());
+        bytes.push(0xEE);
+
+        let (txs, consumed) = BitcoinTransaction::parse_block_transactions(&bytes).unwrap();
+        assert_eq!(txs, vec![genesis, second]);
+        assert_eq!(consumed, bytes.len() - 1);
+
+        // A count too large for the remaining bytes is rejected up front
+        let mut huge = vec![0xFE, 0xFF, 0xFF, 0xFF, 0x00];
+        huge.extend_from_slice(&bytes[1..]);
+        assert_eq!(
+            BitcoinTransaction::parse_block_transactions(&huge),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn test_arbitrary_transactions_roundtrip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        // Deterministic xorshift noise as fuzzer input
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let data: Vec<u8> = (0..256 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+
+        for chunk in data.chunks(8 * 1024) {
+            let tx = BitcoinTransaction::arbitrary(&mut Unstructured::new(chunk)).unwrap();
+            rust_week_3_exercises::testing::assert_roundtrip(
+                &tx,
+                BitcoinTransaction::to_bytes,
+                BitcoinTransaction::from_bytes,
+            );
+        }
+    }
+
+    #[test]
+    fn test_exact_length_accounting() {
+        use rust_week_3_exercises::testing::assert_roundtrip;
+
+        for value in [0, 0xFC, 0xFD, 0xFFFF, 0x1_0000, 0xFFFF_FFFF, u64::MAX] {
+            assert_roundtrip(
+                &CompactSize::new(value),
+                CompactSize::to_bytes,
+                CompactSize::from_bytes,
+            );
+        }
+        let outpoint = OutPoint::new(dummy_txid(9), 3);
+        assert_roundtrip(&outpoint, OutPoint::to_bytes, OutPoint::from_bytes);
+        let script = Script::new(vec![0xAB; 300]);
+        assert_roundtrip(&script, Script::to_bytes, Script::from_bytes);
+        let input = TransactionInput::new(outpoint, script.clone(), 0xFFFF_FFFE);
+        assert_roundtrip(
+            &input,
+            TransactionInput::to_bytes,
+            TransactionInput::from_bytes,
+        );
+        let output = TransactionOutput::new(1_000, script);
+        assert_roundtrip(
+            &output,
+            TransactionOutput::to_bytes,
+            TransactionOutput::from_bytes,
+        );
+
+        let mut tx = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        assert_roundtrip(
+            &tx,
+            BitcoinTransaction::to_bytes,
+            BitcoinTransaction::from_bytes,
+        );
+        tx.inputs[0].witness = vec![vec![0xAB; 3], vec![]].into();
+        assert_roundtrip(
+            &tx,
+            BitcoinTransaction::to_bytes,
+            BitcoinTransaction::from_bytes,
+        );
+    }
+
+    #[test]
+    fn test_tx_hex_roundtrip() {
+        let tx = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        assert_eq!(tx.to_hex(), GENESIS_COINBASE_HEX);
+
+        let odd = &GENESIS_COINBASE_HEX[1..];
+        assert_eq!(
+            BitcoinTransaction::from_hex(odd),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let not_hex = GENESIS_COINBASE_HEX.replacen('0', "g", 1);
+        assert_eq!(
+            BitcoinTransaction::from_hex(&not_hex),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let trailing = format!("{}00", GENESIS_COINBASE_HEX);
+        assert_eq!(
+            BitcoinTransaction::from_hex(&trailing),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_lock_time_classification() {
+        assert_eq!(LockTime::from_u32(0), LockTime::Blocks(0));
+        assert_eq!(
+            LockTime::from_u32(499_999_999),
+            LockTime::Blocks(499_999_999)
+        );
+        assert_eq!(LockTime::from_u32(500_000_000), LockTime::Time(500_000_000));
+        assert_eq!(LockTime::Time(1_700_000_000).to_u32(), 1_700_000_000);
+
+        let tx = TransactionBuilder::new().lock_time(840_000).build();
+        assert_eq!(tx.lock_time_parsed(), LockTime::Blocks(840_000));
+    }
+
+    #[test]
+    fn test_is_final() {
+        let mut tx = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFE,
+            )
+            .build();
+        assert!(tx.is_final(0, 0));
+
+        tx.lock_time = 100;
+        assert!(!tx.is_final(100, u32::MAX));
+        assert!(tx.is_final(101, 0));
+
+        tx.lock_time = 1_600_000_000;
+        assert!(!tx.is_final(u32::MAX, 1_600_000_000));
+        assert!(tx.is_final(0, 1_600_000_001));
+
+        // Final sequences on every input override an unmet lock time
+        tx.inputs[0].sequence = 0xFFFFFFFF;
+        assert!(tx.is_final(0, 0));
+    }
+
+    #[test]
+    fn test_signal_and_clear_rbf() {
+        let mut tx = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            )
+            .add_input(
+                OutPoint::new(dummy_txid(2), 0),
+                Script::new(vec![]),
+                0xFFFFFFFE,
+            )
+            .add_input(OutPoint::new(dummy_txid(3), 0), Script::new(vec![]), 144)
+            .build();
+
+        tx.signal_rbf();
+        let sequences: Vec<u32> = tx.inputs.iter().map(|i| i.sequence).collect();
+        assert_eq!(sequences, vec![0xFFFFFFFD, 0xFFFFFFFD, 144]);
+
+        tx.clear_rbf();
+        let sequences: Vec<u32> = tx.inputs.iter().map(|i| i.sequence).collect();
+        assert_eq!(sequences, vec![0xFFFFFFFE, 0xFFFFFFFE, 144]);
+    }
+
+    #[test]
+    fn test_bip68_requires_version_2() {
+        let mut tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 10)
+            .build();
+        assert!(tx.is_bip68_active());
+        assert_eq!(
+            tx.input_relative_locktime(0),
+            Some(RelativeLockTime::Blocks(10))
+        );
+        assert_eq!(tx.input_relative_locktime(1), None);
+
+        tx.version = 1;
+        assert!(!tx.is_bip68_active());
+        assert_eq!(tx.input_relative_locktime(0), None);
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let outpoint = OutPoint::new(dummy_txid(1), 0);
+        let mut tx = TransactionBuilder::new()
+            .add_input(outpoint.clone(), Script::new(vec![]), 0)
+            .add_output(MAX_MONEY, Script::new(vec![0x51]))
+            .build();
+        assert_eq!(tx.validate_structure(), Ok(()));
+
+        let reason = |tx: &BitcoinTransaction| match tx.validate_structure() {
+            Err(BitcoinError::InvalidTransaction(reason)) => reason,
+            other => panic!("unexpected {:?}", other),
+        };
+
+        tx.outputs[0].value = MAX_MONEY + 1;
+        assert_eq!(reason(&tx), "bad-txns-vout-toolarge");
+        tx.outputs[0].value = MAX_MONEY;
+        tx.outputs
+            .push(TransactionOutput::new(1, Script::new(vec![0x51])));
+        assert_eq!(reason(&tx), "bad-txns-txouttotal-toolarge");
+        tx.outputs[1].value = u64::MAX;
+        tx.outputs[0].value = 1;
+        assert_eq!(reason(&tx), "bad-txns-vout-toolarge");
+        tx.outputs.truncate(1);
+
+        tx.inputs
+            .push(TransactionInput::new(outpoint, Script::new(vec![0x51]), 1));
+        assert_eq!(reason(&tx), "bad-txns-inputs-duplicate");
+        tx.inputs[1].previous_output.vout = 1;
+        assert_eq!(tx.validate_structure(), Ok(()));
+
+        let no_outputs = BitcoinTransaction::new(2, tx.inputs.clone(), vec![], 0);
+        assert_eq!(reason(&no_outputs), "bad-txns-vout-empty");
+        assert_eq!(reason(&BitcoinTransaction::default()), "bad-txns-vin-empty");
+    }
+
+    #[test]
+    fn test_standardness() {
+        let p2pkh = output_with_script("76a914111111111111111111111111111111111111111188ac");
+        let standard = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![0x51]), 0)
+            .add_output(10_000, p2pkh.script_pubkey.clone())
+            .build();
+        assert!(standard.is_standard());
+        assert_eq!(standard.standardness_error(), None);
+
+        let mut tx = standard.clone();
+        tx.version = 3;
+        assert_eq!(tx.standardness_error(), Some("version"));
+
+        let mut tx = standard.clone();
+        tx.outputs.clear();
+        assert_eq!(tx.standardness_error(), Some("bad-txns-vout-empty"));
+
+        let mut tx = standard.clone();
+        tx.inputs[0].script_sig = Script::new(vec![0x00; 1651]);
+        assert_eq!(tx.standardness_error(), Some("scriptsig-size"));
+
+        let mut tx = standard.clone();
+        tx.outputs
+            .push(TransactionOutput::new(1, Script::new(vec![0x51])));
+        assert!(!tx.is_standard());
+        assert_eq!(tx.standardness_error(), Some("scriptpubkey"));
+
+        assert!(!standard.exceeds_standard_weight());
+        let mut tx = standard;
+        tx.outputs = vec![p2pkh; MAX_STANDARD_TX_WEIGHT / 4 / 34];
+        assert!(tx.exceeds_standard_weight());
+        assert_eq!(tx.standardness_error(), Some("tx-size"));
+    }
+
+    #[test]
+    fn test_is_coinbase() {
+        let coinbase = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        assert!(coinbase.is_coinbase());
+        assert!(coinbase.inputs[0].previous_output.is_null());
+
+        let null_input = TransactionInput::new(OutPoint::null(), Script::new(vec![]), 0);
+        let spend = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        assert!(!OutPoint::new([0u8; 32], 0).is_null());
+
+        let tx = BitcoinTransaction::new(1, vec![spend.clone()], vec![], 0);
+        assert!(!tx.is_coinbase());
+        let tx = BitcoinTransaction::new(1, vec![null_input.clone(), spend], vec![], 0);
+        assert!(!tx.is_coinbase());
+        let tx = BitcoinTransaction::new(1, vec![], vec![], 0);
+        assert!(!tx.is_coinbase());
+    }
+
+    #[cfg(feature = "bitcoin-compat")]
+    #[test]
+    fn test_bitcoin_crate_conversions() {
+        for (name, tx_hex, ..) in MAINNET_TXS {
+            let tx = BitcoinTransaction::from_hex(tx_hex).unwrap();
+            let theirs = bitcoin::Transaction::try_from(&tx).unwrap();
+            assert_eq!(
+                theirs.compute_txid().to_string(),
+                tx.txid().to_rpc_string(),
+                "{}",
+                name
+            );
+            assert_eq!(
+                BitcoinTransaction::try_from(&theirs),
+                Ok(tx.clone()),
+                "{}",
+                name
+            );
+
+            let outpoint = tx.inputs[0].previous_output.clone();
+            let converted = bitcoin::OutPoint::from(&outpoint);
+            assert_eq!(converted.to_string(), outpoint.to_string(), "{}", name);
+            assert_eq!(OutPoint::from(converted), outpoint);
+
+            let script = tx.outputs[0].script_pubkey.clone();
+            assert_eq!(Script::from(bitcoin::ScriptBuf::from(&script)), script);
+        }
+
+        let empty = BitcoinTransaction::new(2, vec![], vec![], 0);
+        assert!(bitcoin::Transaction::try_from(&empty).is_err());
+    }
+
+    #[test]
+    fn test_weight_matches_reference_values() {
+        // Reference weights, computed independently with rust-bitcoin 0.32
+        let weights = [752, 1468, 450, 617];
+        for ((name, tx_hex, ..), weight) in MAINNET_TXS.into_iter().zip(weights) {
+            let tx = BitcoinTransaction::from_hex(tx_hex).unwrap();
+            let len = tx_hex.len() / 2;
+            assert_eq!(tx.weight(), weight, "{}", name);
+            assert_eq!(tx.vsize(), weight.div_ceil(4), "{}", name);
+            if !tx.has_witness() {
+                assert_eq!(tx.weight(), 4 * tx.serialized_len(), "{}", name);
+                assert_eq!(tx.vsize(), len, "{}", name);
+            }
+        }
+
+        // The P2WPKH spend: 195 raw bytes but only 113 virtual bytes
+        let p2wpkh = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        assert_eq!((p2wpkh.size(), p2wpkh.vsize()), (195, 113));
+    }
+
+    #[test]
+    fn test_weight_and_vsize() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (legacy, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(legacy.size(), bytes.len());
+        assert_eq!(legacy.weight(), 4 * bytes.len());
+        assert_eq!(legacy.vsize(), bytes.len());
+        assert_eq!(legacy.serialized_len(), bytes.len());
+        assert_eq!(legacy.to_bytes().capacity(), bytes.len());
+
+        let mut segwit = legacy.clone();
+        segwit.inputs[0].witness = vec![vec![0xAB; 3]].into();
+        let witness_bytes = 2 + 1 + 1 + 3;
+        assert_eq!(segwit.size(), segwit.to_bytes().len());
+        assert_eq!(segwit.to_bytes().capacity(), segwit.serialized_len());
+        assert_eq!(segwit.size(), bytes.len() + witness_bytes);
+        assert_eq!(segwit.weight(), 4 * bytes.len() + witness_bytes);
+        assert_eq!(
+            segwit.vsize(),
+            (4 * bytes.len() + witness_bytes).div_ceil(4)
+        );
+    }
+
+    #[test]
+    fn test_size_breakdown() {
+        let tx = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        let breakdown = tx.size_breakdown();
+        assert_eq!(
+            breakdown,
+            SizeBreakdown {
+                version: 4,
+                input_count_varint: 1,
+                inputs: 41,
+                output_count_varint: 1,
+                outputs: 34,
+                witness: 2 + 1 + (1 + 72) + (1 + 33),
+                locktime: 4,
+                total: 195,
+            }
+        );
+        assert_eq!(breakdown.total, tx.size());
+        assert_eq!(
+            tx.weight(),
+            4 * (breakdown.total - breakdown.witness) + breakdown.witness
+        );
+        assert_eq!(tx.strip_witness().size_breakdown().witness, 0);
+    }
+
+    #[test]
+    fn test_transaction_builder() {
+        let tx = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x01]),
+                0xFFFFFFFF,
+            )
+            .add_output(5_000, Script::new(vec![0x51]))
+            .build();
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.lock_time, 0);
+
+        let expected = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x01]),
+                0xFFFFFFFF,
+            )],
+            vec![TransactionOutput::new(5_000, Script::new(vec![0x51]))],
+            0,
+        );
+        assert_eq!(tx, expected);
+
+        let tx = TransactionBuilder::new().version(1).lock_time(500).build();
+        assert_eq!((tx.version, tx.lock_time), (1, 500));
+    }
+
+    #[test]
+    fn test_sort_bip69() {
+        // `a` sorts first in display order even though `b` is smaller in
+        // internal byte order.
+        let mut a = [0u8; 32];
+        a[0] = 0x02;
+        a[31] = 0x01;
+        let mut b = [0u8; 32];
+        b[0] = 0x01;
+        b[31] = 0x02;
+
+        let mut tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(b, 0), Script::new(vec![]), 0)
+            .add_input(OutPoint::new(a, 1), Script::new(vec![]), 0)
+            .add_input(OutPoint::new(a, 0), Script::new(vec![]), 0)
+            .add_output(2_000, Script::new(vec![0x51]))
+            .add_output(1_000, Script::new(vec![0x52]))
+            .add_output(1_000, Script::new(vec![0x51, 0x00]))
+            .build();
+        tx.inputs[0].push_witness_item(vec![0xB0]);
+        tx.inputs[1].push_witness_item(vec![0xA1]);
+        tx.inputs[2].push_witness_item(vec![0xA0]);
+
+        let sorted = tx.sorted_bip69();
+        let outpoints: Vec<_> = sorted
+            .inputs
+            .iter()
+            .map(|input| input.previous_output.clone())
+            .collect();
+        assert_eq!(
+            outpoints,
+            vec![
+                OutPoint::new(a, 0),
+                OutPoint::new(a, 1),
+                OutPoint::new(b, 0)
+            ]
+        );
+        let witnesses: Vec<_> = sorted
+            .inputs
+            .iter()
+            .map(TransactionInput::witness_items)
+            .collect();
+        assert_eq!(witnesses, [[vec![0xA0]], [vec![0xA1]], [vec![0xB0]]]);
+        let outputs: Vec<_> = sorted
+            .outputs
+            .iter()
+            .map(|output| (output.value, output.script_pubkey.bytes.clone()))
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                (1_000, vec![0x51, 0x00]),
+                (1_000, vec![0x52]),
+                (2_000, vec![0x51]),
+            ]
+        );
+
+        tx.sort_bip69();
+        assert_eq!(tx, sorted);
+    }
+
+    #[test]
+    fn test_total_output_value() {
+        let mut tx = TransactionBuilder::new()
+            .add_output(1_000, Script::new(vec![0x51]))
+            .add_output(2_500, Script::new(vec![0x52]))
+            .build();
+        assert_eq!((tx.input_count(), tx.output_count()), (0, 2));
+        assert_eq!(tx.total_output_value(), Ok(3_500));
+
+        tx.outputs[0].value = u64::MAX;
+        assert_eq!(tx.total_output_value(), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_indexed_iterators() {
+        let tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .add_input(OutPoint::new(dummy_txid(2), 5), Script::new(vec![]), 0)
+            .add_output(1_000, Script::new(vec![0x51]))
+            .build();
+
+        let spent: std::collections::HashSet<&OutPoint> = tx.spent_outpoints().collect();
+        assert!(spent.contains(&OutPoint::new(dummy_txid(2), 5)));
+        assert_eq!(spent.len(), 2);
+
+        let indices: Vec<usize> = tx.inputs_with_index().map(|(i, _)| i).collect();
+        assert_eq!(indices, [0, 1]);
+        let (index, output) = tx.outputs_with_index().next().unwrap();
+        assert_eq!((index, output.value), (0, 1_000));
+    }
+
+    #[test]
+    fn test_checked_accessors() {
+        let tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .add_output(1_000, Script::new(vec![0x51]))
+            .build();
+        assert_eq!(tx.input(0), Some(&tx.inputs[0]));
+        assert_eq!(tx.input(1), None);
+        assert_eq!(tx.output(0).map(|output| output.value), Some(1_000));
+        assert_eq!(tx.output(1), None);
+
+        let script = Script::new(vec![0x51]);
+        assert_eq!(
+            tx.sighash_segwit(1, &script, 0, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            tx.sighash_legacy(1, &script, SighashType::Single),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_fee_and_fee_rate() {
+        let tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .add_input(OutPoint::new(dummy_txid(2), 1), Script::new(vec![]), 0)
+            .add_output(14_000, Script::new(vec![0x51]))
+            .build();
+        let prevouts = vec![
+            TransactionOutput::new(10_000, Script::new(vec![])),
+            TransactionOutput::new(5_000, Script::new(vec![])),
+        ];
+
+        assert_eq!(tx.fee(&prevouts), Ok(1_000));
+        assert_eq!(tx.fee_rate(&prevouts).unwrap(), 1_000.0 / tx.vsize() as f64);
+
+        assert_eq!(tx.fee(&prevouts[..1]), Err(BitcoinError::InvalidFormat));
+        let short = vec![
+            TransactionOutput::new(10_000, Script::new(vec![])),
+            TransactionOutput::new(1_000, Script::new(vec![])),
+        ];
+        assert_eq!(tx.fee(&short), Err(BitcoinError::InvalidFormat));
+        let overflowing = vec![
+            TransactionOutput::new(u64::MAX, Script::new(vec![])),
+            TransactionOutput::new(1, Script::new(vec![])),
+        ];
+        assert_eq!(tx.fee(&overflowing), Err(BitcoinError::InvalidFormat));
+    }
+
+    fn hex_array(s: &str) -> [u8; 32] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_sighash_type_conversions() {
+        for value in [0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+            let sighash_type = SighashType::from_u32(value).unwrap();
+            assert_eq!(sighash_type.to_u32(), value);
+            assert_eq!(
+                sighash_type.is_anyonecanpay(),
+                value & SIGHASH_ANYONECANPAY != 0
+            );
+            assert_eq!(sighash_type.base_type().to_u32(), value & 0x1f);
+        }
+        assert_eq!(
+            SighashType::SinglePlusAnyoneCanPay.base_type(),
+            SighashType::Single
+        );
+        for value in [0x00, 0x04, 0x80, 0x84, 0x101] {
+            assert_eq!(SighashType::from_u32(value), None);
+        }
+    }
+
+    // Test vectors from BIP143
+    #[test]
+    fn test_sighash_segwit_p2wpkh() {
+        let tx = BitcoinTransaction::from_hex(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000\
+             00eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000\
+             ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac909351\
+             0d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+        )
+        .unwrap();
+        let script_code =
+            Script::new(hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap());
+
+        assert_eq!(
+            tx.sighash_segwit(1, &script_code, 600_000_000, SighashType::All)
+                .unwrap(),
+            hex_array("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670")
+        );
+        assert_eq!(
+            tx.sighash_segwit(2, &script_code, 600_000_000, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_sighash_segwit_p2wsh_all_types() {
+        let tx = BitcoinTransaction::from_hex(
+            "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000\
+             ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f\
+             05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000",
+        )
+        .unwrap();
+        let witness_script = Script::new(
+            hex::decode(
+                "56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28\
+                 bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b\
+                 9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58\
+                 c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b1486\
+                 2c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b\
+                 56ae",
+            )
+            .unwrap(),
+        );
+
+        let cases = [
+            (
+                SighashType::All,
+                "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+            ),
+            (
+                SighashType::None,
+                "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+            ),
+            (
+                SighashType::Single,
+                "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+            ),
+            (
+                SighashType::AllPlusAnyoneCanPay,
+                "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+            ),
+            (
+                SighashType::NonePlusAnyoneCanPay,
+                "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+            ),
+            (
+                SighashType::SinglePlusAnyoneCanPay,
+                "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+            ),
+        ];
+        // One cache serves every sighash type
+        let cache = SighashCache::new(&tx);
+        for (sighash_type, expected) in cases {
+            assert_eq!(
+                tx.sighash_segwit(0, &witness_script, 987_654_321, sighash_type)
+                    .unwrap(),
+                hex_array(expected),
+                "sighash type {:?}",
+                sighash_type
+            );
+            assert_eq!(
+                cache.segwit_signature_hash(0, &witness_script, 987_654_321, sighash_type),
+                Ok(hex_array(expected)),
+                "sighash type {:?}",
+                sighash_type
+            );
+        }
+        assert_eq!(
+            cache.segwit_signature_hash(1, &witness_script, 0, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    const LEGACY_SIGHASH_TX_HEX: &str = "010000000301010101010101010101010101010101010101010101010101010101010101010000000002\
+             5152feffffff020202020202020202020202020202020202020202020202020202020202020201000000\
+             025152fdffffff0303030303030303030303030303030303030303030303030303030303030303020000\
+             00025152fcffffff0288130000000000000151581b000000000000036a01017b000000";
+
+    #[test]
+    fn test_sighash_legacy_all_types() {
+        let tx = BitcoinTransaction::from_hex(LEGACY_SIGHASH_TX_HEX).unwrap();
+        let script_pubkey =
+            Script::new(hex::decode("76a914111111111111111111111111111111111111111188ac").unwrap());
+
+        let cases = [
+            (
+                SighashType::All,
+                "2cd3bcd468ba34d0b51faab65e42e732cb3634be4ae108887e00417ecff1a6d0",
+            ),
+            (
+                SighashType::None,
+                "415a0fccc0ef4a6004815bffad7c264834755c05834d287ec983d8081518bd35",
+            ),
+            (
+                SighashType::Single,
+                "06e3c666bf00ac19468e1cbb1715bd77a9db8f8c503f03ed895d182e6ea08823",
+            ),
+            (
+                SighashType::AllPlusAnyoneCanPay,
+                "1da9d4e73babe9e4ef46642ab09af6f46e6c37e67d9d62a0084cd29186585939",
+            ),
+            (
+                SighashType::NonePlusAnyoneCanPay,
+                "c9f205d9e69a7bd21ad82ec102ca473bd1b4f5e225d8b53eff881eb806448d63",
+            ),
+            (
+                SighashType::SinglePlusAnyoneCanPay,
+                "eadb2e3d817908d5627df36ef77899a77e3f1c255736121a0f570fe4f52b7ded",
+            ),
+        ];
+        for (sighash_type, expected) in cases {
+            assert_eq!(
+                tx.sighash_legacy(1, &script_pubkey, sighash_type).unwrap(),
+                hex_array(expected),
+                "sighash type {:?}",
+                sighash_type
+            );
+        }
+
+        // OP_CODESEPARATOR is stripped from the script code.
+        let mut with_separator = vec![0xab];
+        with_separator.extend_from_slice(&script_pubkey);
+        assert_eq!(
+            tx.sighash_legacy(1, &Script::new(with_separator), SighashType::All),
+            tx.sighash_legacy(1, &script_pubkey, SighashType::All)
+        );
+    }
+
+    #[test]
+    fn test_sighash_legacy_single_without_output() {
+        let tx = BitcoinTransaction::from_hex(LEGACY_SIGHASH_TX_HEX).unwrap();
+        let script_pubkey = Script::new(vec![0x51]);
+        let mut one = [0u8; 32];
+        one[0] = 1;
+
+        assert_eq!(
+            tx.sighash_legacy(2, &script_pubkey, SighashType::Single),
+            Ok(one)
+        );
+        assert_eq!(
+            tx.sighash_legacy(3, &script_pubkey, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    // Mainnet transactions: (description, raw hex, txid, wtxid, witness item
+    // lengths of the first input)
+    const MAINNET_TXS: [(&str, &str, &str, &str, &[usize]); 4] = [
+        (
+            "P2PKH spend",
+            "0200000001725aab4d23f76ad10bb569a68f8702ebfb8b076e015179ff9b9425234953ac6300000000\
+             6a47304402204cae7dc9bb68b588dd6b8afb8b881b752fd65178c25693ea6d5d9a08388fd2a2022011\
+             c753d522d5c327741a6d922342c86e05c928309d7e566f688148432e887028012103f14b11cfb58b11\
+             3716e0fa277ab4a32e4d3ed64c6b09b1747ef7c828d5b06a94fdffffff01e5d4830100000000160014\
+             e98527b55cae861e5b9c3a679486514c012d6fce00000000",
+            "08af3b0762fccdac538255932978d8e99eddc58affb62d951f73b1f7054e3fb4",
+            "08af3b0762fccdac538255932978d8e99eddc58affb62d951f73b1f7054e3fb4",
+            &[],
+        ),
+        (
+            "P2SH 2-of-3 multisig spend",
+            "010000000115fe9ec3dc964e41f5267ea26cfe505f202bf3b292627496b04bece84da9b18903000000\
+             fc004730440220442827f1085364bda58c5884cee7b289934083362db6dfb627dc46f6cdbf57930220\
+             78cfa524252c381f2a572f0c41486e2838ca94aa268f2384d0e515744bf0e1e9014730440220160e49\
+             536bb29a49c7626744ee83150174c22fa40d58fb4cd554a907a6a7b825022045f6cf148504b3340646\
+             86795f0968c689e542f475b8ef5a5fa42383948226a3014c69522103e54bc61efbcb8eeff3a5ab2a92\
+             a75272f5f6820e38e3d28edb54beb06b86c0862103a553e30733d7a8df6d390d59cc136e2c9d9cf4e8\
+             08f3b6ab009beae68dd60822210291c5a54bb8b00b6f72b90af0ac0ecaf78fab026d8eded282ad95d4\
+             d65db268c953aeffffffff024c4f0d000000000017a9146ebf0484bd5053f727c755a750aa4c815dfa\
+             112887a06b12020000000017a91410065dd50b3a7f299fef3b1c53b8216399916ab08700000000",
+            "c60769c9f0cf53629464a119c9e13527a61042349cbfa9d571f60e29af794f73",
+            "c60769c9f0cf53629464a119c9e13527a61042349cbfa9d571f60e29af794f73",
+            &[],
+        ),
+        (
+            "P2WPKH spend",
+            "020000000001018c47330b1c4d30e7e2244e8ccb56d411b71e10073bb42fa1813f3f01e144cc4d0100\
+             000000fdffffff01f7e30300000000001976a9143b49fd16f7562cfeedc6a4ba84805f8c2f8e1a2c88\
+             ac024830450221009a4dbf077a63f6e4c3628a5fef2a09ec6f7ca4a4d95bc8bb69195b6b671e927202\
+             2074da9ffff5a677fc7b37d66bb4ff1f316c9dbacb92058291d84cd4b83f7c63c9012103d013e9e53c\
+             9ca8dd2ddffab1e9df27811503feea7eb0700ff058851bbb37d99000000000",
+            "c060c581dbaa39d0803f91a0146343f851cfc29c833b2c3fbe04182ea874cb18",
+            "71621e00d545aba64b3fe61e6ae71392475800eadb4157e4551c5467d86ad789",
+            &[72, 33],
+        ),
+        (
+            "P2TR key path spend",
+            "01000000000101b5cee87f1a60915c38bb0bc26aaf2b67be2b890bbc54bb4be1e40272e0d2fe0b0000\
+             000000ffffffff025529000000000000225120106daad8a5cb2e6fc74783714273bad554a148ca2d05\
+             4e7a19250e9935366f3033760000000000002200205e6d83c44f57484fd2ef2a62b6d36cdcd6b3e06b\
+             661e33fd65588a28ad0dbe060141df9d1bfce71f90d68bf9e9461910b3716466bfe035c7dbabaa7791\
+             383af6c7ef405a3a1f481488a91d33cd90b098d13cb904323a3e215523aceaa04e1bb35cdb01000000\
+             00",
+            "9ba9d23c3e21d1d2f3fd658cd60043553c80cdae7885b5ee29c10cda4b5b715f",
+            "422e5be8c7c506f4be0b64f5e2c2bc2a5a463bc6f6a4c6f2bafdccff1831c55d",
+            &[65],
+        ),
+    ];
+
+    #[test]
+    fn test_mainnet_transactions_roundtrip() {
+        for (name, tx_hex, txid, wtxid, witness_lens) in MAINNET_TXS {
+            let bytes = hex::decode(tx_hex).unwrap();
+            let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(consumed, bytes.len(), "{}", name);
+            assert_eq!(tx.to_bytes(), bytes, "{}", name);
+            assert_eq!(tx.txid().to_string(), txid, "{}", name);
+            assert_eq!(tx.wtxid().to_string(), wtxid, "{}", name);
+
+            let lens: Vec<usize> = tx.inputs[0].witness.iter().map(Vec::len).collect();
+            assert_eq!(lens, witness_lens, "{}", name);
+        }
+
+        let (_, multisig, ..) = MAINNET_TXS[1];
+        let tx = BitcoinTransaction::from_hex(multisig).unwrap();
+        let pushes = tx.inputs[0].script_sig.parse_pushes().unwrap();
+        assert_eq!(pushes.len(), 4);
+        assert_eq!(pushes[3].last(), Some(&0xae));
+
+        let (_, taproot, ..) = MAINNET_TXS[3];
+        let tx = BitcoinTransaction::from_hex(taproot).unwrap();
+        assert_eq!(tx.outputs[0].script_pubkey.script_type(), ScriptType::P2TR);
+    }
+
+    #[test]
+    fn test_sigop_count() {
+        let p2pkh =
+            Script::new(hex::decode("76a914111111111111111111111111111111111111111188ac").unwrap());
+        assert_eq!(p2pkh.sigop_count(false), 1);
+
+        // 2 <key> <key> <key> 3 OP_CHECKMULTISIG, then OP_CHECKSIGVERIFY
+        let mut multisig = vec![0x52];
+        for _ in 0..3 {
+            multisig.push(33);
+            multisig.extend_from_slice(&[0x02; 33]);
+        }
+        multisig.extend_from_slice(&[0x53, 0xae, 0xad]);
+        let multisig = Script::new(multisig);
+        assert_eq!(multisig.sigop_count(false), 21);
+        assert_eq!(multisig.sigop_count(true), 4);
+
+        // Without a preceding OP_N the legacy count applies in both modes
+        let bare = Script::new(vec![0xaf]);
+        assert_eq!(bare.sigop_count(true), 20);
+        let pushed_n = Script::new(vec![0x01, 0x03, 0xae]);
+        assert_eq!(pushed_n.sigop_count(true), 20);
+
+        // Counting stops at a truncated push
+        assert_eq!(Script::new(vec![0xac, 0x4c]).sigop_count(false), 1);
+
+        let p2pkh_spend = BitcoinTransaction::from_hex(MAINNET_TXS[0].1).unwrap();
+        assert_eq!(p2pkh_spend.total_sigops(), 0);
+        let pays_p2pkh = BitcoinTransaction::from_hex(MAINNET_TXS[2].1).unwrap();
+        assert_eq!(pays_p2pkh.total_sigops(), 1);
+        let multisig_spend = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![0xac]), 0)
+            .add_output(1_000, multisig)
+            .build();
+        assert_eq!(multisig_spend.total_sigops(), 22);
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        // Block 100000.
+        let txids: Vec<Txid> = [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+
+        assert_eq!(
+            merkle_root(&txids).unwrap().to_rpc_string(),
+            "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+        );
+        // Odd level: the last node is paired with itself.
+        assert_eq!(
+            merkle_root(&txids[..3]).unwrap().to_rpc_string(),
+            "fa435470825de273081dcc706b25514c936fa6dc80ab965ce6970d68ddd0b553"
+        );
+        assert_eq!(merkle_root(&txids[..1]), Some(txids[0].clone()));
+        assert_eq!(merkle_root(&[]), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bitcoin_tx_json_serialization() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xAB), 3),
+            Script::new(vec![0xDE, 0xAD, 0xBE, 0xEF]),
+            0xABCDEF01,
+        );
+        let tx = BitcoinTransaction::new(1, vec![input], vec![], 999);
+
+        let json = serde_json::to_string_pretty(&tx).unwrap();
+        let parsed: BitcoinTransaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(tx, parsed);
+
+        assert!(json.contains("\"version\": 1"));
+        assert!(json.contains("\"lock_time\": 999"));
+    }
+
+    #[cfg(feature = "bitcoind-json")]
+    #[test]
+    fn test_bitcoind_verbose_json() {
+        use rust_week_3_exercises::json::RawTransaction;
+
+        let coinbase = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let json = serde_json::to_value(RawTransaction::from(&coinbase)).unwrap();
+        assert_eq!(
+            json["txid"],
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["locktime"], 0);
+        assert_eq!(json["size"], 204);
+        assert!(
+            json["vin"][0]["coinbase"]
+                .as_str()
+                .unwrap()
+                .starts_with("04ffff001d")
+        );
+        assert!(json["vin"][0].get("txid").is_none());
+        assert_eq!(json["vin"][0]["sequence"], 4294967295u32);
+        assert_eq!(json["vout"][0]["value"], "50.00000000");
+        assert_eq!(json["vout"][0]["n"], 0);
+        assert!(
+            json["vout"][0]["scriptPubKey"]["hex"]
+                .as_str()
+                .unwrap()
+                .ends_with("ac")
+        );
+
+        let spend = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(0xAB), 3),
+                Script::new(vec![0x51]),
+                7,
+            )
+            .add_output(12_345, Script::new(vec![0x6A]))
+            .build();
+        let json = serde_json::to_value(RawTransaction::from(&spend)).unwrap();
+        assert_eq!(
+            json["vin"][0]["txid"],
+            Txid(dummy_txid(0xAB)).to_rpc_string()
+        );
+        assert_eq!(json["vin"][0]["vout"], 3);
+        assert_eq!(json["vin"][0]["scriptSig"]["hex"], "51");
+        assert_eq!(json["vin"][0]["scriptSig"]["asm"], "1");
+        assert!(json["vin"][0].get("txinwitness").is_none());
+        assert_eq!(json["vout"][0]["value"], "0.00012345");
+        assert_eq!(json["vout"][0]["scriptPubKey"]["hex"], "6a");
+        assert_eq!(json["vout"][0]["scriptPubKey"]["asm"], "OP_RETURN");
+
+        // Exact even where an f64 could not hold every satoshi
+        let mut crafted = spend.clone();
+        crafted.outputs[0].value = u64::MAX;
+        let json = serde_json::to_value(RawTransaction::from(&crafted)).unwrap();
+        assert_eq!(json["vout"][0]["value"], "184467440737.09551615");
+    }
+
+    #[test]
+    fn test_bitcoin_transaction_display() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xCD), 7),
+            Script::new(vec![0x01, 0x02, 0x03]),
+            0xFFFFFFFF,
+        );
+        let tx = BitcoinTransaction::new(1, vec![input], vec![], 0);
+        let output = format!("{}", tx);
+        assert!(output.contains("Version: 1"));
+        assert!(output.contains("Lock Time: 0"));
+        assert!(output.contains("Previous Output Vout: 7"));
+        let p2pkh = output_with_script("76a914111111111111111111111111111111111111111188ac");
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![],
+            vec![TransactionOutput::new(5_000_000_001, p2pkh.script_pubkey)],
+            0,
+        );
+        let output = format!("{}", tx);
+        assert!(output.contains("Output Count: 1"));
+        assert!(output.contains(
+            "Output 0: 5000000001 sat (50.00000001 BTC), ScriptPubKey: \
+             76a914111111111111111111111111111111111111111188ac (P2PKH)"
+        ));
+    }
+
+    #[test]
+    fn test_display_with_prevouts() {
+        let tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .add_input(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 0)
+            .build();
+        let mut prevout = output_with_script("76a914111111111111111111111111111111111111111188ac");
+        prevout.value = 150_000;
+        let address = prevout.address(Network::Mainnet).unwrap();
+
+        let output = tx.display_with_prevouts(&[prevout]);
+        assert!(output.contains(&format!(
+            "Input 0:\n  Previous Output: {}\n  Previous Output Vout: 0\n  \
+             ScriptSig Length: 0 bytes\n  ScriptSig: \n  Sequence: 0\n  \
+             Spent Value: 0.00150000 BTC\n  Spent Address: {}\n",
+            hex::encode(dummy_txid(1)),
+            address
+        )));
+        assert!(output.contains(
+            "Sequence: 0\n  Spent Value: <unknown>\n  Spent Address: <unknown>\nOutput Count"
+        ));
+        assert!(!tx.to_string().contains("Spent"));
+    }
+
+    #[test]
+    fn test_script_raw_and_wire_bytes() {
+        let script = Script::new(vec![0x51, 0x52, 0x93]);
+        assert_eq!(script.raw_bytes(), &[0x51, 0x52, 0x93]);
+        assert_eq!(script.raw_bytes(), &script[..]);
+        assert_eq!(script.to_wire_bytes(), vec![0x03, 0x51, 0x52, 0x93]);
+        assert_eq!(script.to_wire_bytes(), script.to_bytes());
+    }
+
+    #[test]
+    fn test_script_display() {
+        let script = Script::new(vec![0x76, 0xA9, 0x02, 0xAB, 0xCD, 0x87]);
+        assert_eq!(script.to_string(), "OP_DUP OP_HASH160 <2 bytes> OP_EQUAL");
+        // A truncated push falls back to hex
+        assert_eq!(Script::new(vec![0x76, 0x05, 0x01]).to_string(), "760501");
+    }
+}
+
+/// Serializations checked against bytes laid out by hand, so a bug shared by
+/// the encoder and the parser cannot hide behind a round trip.
+#[cfg(test)]
+mod reference_bytes {
+    use super::*;
+
+    #[test]
+    fn compact_size_boundaries() {
+        let cases: [(u64, &[u8]); 9] = [
+            (0, &[0x00]),
+            (0xFC, &[0xFC]),
+            (0xFD, &[0xFD, 0xFD, 0x00]),
+            (0xFFFF, &[0xFD, 0xFF, 0xFF]),
+            (0x1_0000, &[0xFE, 0x00, 0x00, 0x01, 0x00]),
+            (0xFFFF_FFFF, &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF]),
+            (
+                0x1_0000_0000,
+                &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+            ),
+            (
+                0x0102_0304_0506_0708,
+                &[0xFF, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+            ),
+            (
+                u64::MAX,
+                &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+            ),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(CompactSize::new(value).to_bytes(), expected, "{:#x}", value);
+        }
+    }
+
+    #[test]
+    fn multi_input_transaction() {
+        let mut txid_a = [0u8; 32];
+        txid_a[0] = 0xAA;
+        let mut txid_b = [0u8; 32];
+        txid_b[31] = 0xBB;
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![
+                TransactionInput::new(
+                    OutPoint::new(txid_a, 0),
+                    Script::new(vec![0x51]),
+                    0xFFFFFFFF,
+                ),
+                TransactionInput::new(
+                    OutPoint::new(txid_b, 0x0102),
+                    Script::new(vec![]),
+                    0xFFFFFFFD,
+                ),
+            ],
+            vec![TransactionOutput::new(0x0102_0304, Script::new(vec![0x6A]))],
+            0x0000_1234,
+        );
+
+        let mut expected = vec![0x01, 0x00, 0x00, 0x00]; // version
+        expected.push(0x02); // input count
+        expected.push(0xAA);
+        expected.extend_from_slice(&[0x00; 31]);
+        expected.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // vout 0
+        expected.extend_from_slice(&[0x01, 0x51]); // scriptSig OP_1
+        expected.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        expected.extend_from_slice(&[0x00; 31]);
+        expected.push(0xBB);
+        expected.extend_from_slice(&[0x02, 0x01, 0x00, 0x00]); // vout 0x0102
+        expected.push(0x00); // empty scriptSig
+        expected.extend_from_slice(&[0xFD, 0xFF, 0xFF, 0xFF]);
+        expected.push(0x01); // output count
+        expected.extend_from_slice(&[0x04, 0x03, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00]);
+        expected.extend_from_slice(&[0x01, 0x6A]); // scriptPubKey OP_RETURN
+        expected.extend_from_slice(&[0x34, 0x12, 0x00, 0x00]); // lock time
+
+        assert_eq!(tx.to_bytes(), expected);
+        assert_eq!(tx.size(), 4 + 1 + 42 + 41 + 1 + 10 + 4);
+    }
+
+    #[test]
+    fn script_with_three_byte_length() {
+        // 253 bytes is the shortest script whose length needs the 0xFD form
+        let script = Script::new(vec![0x00; 253]);
+        let bytes = script.to_bytes();
+        assert_eq!(bytes[..3], [0xFD, 0xFD, 0x00]);
+        assert_eq!(bytes.len(), 3 + 253);
+        assert!(bytes[3..].iter().all(|&b| b == 0x00));
+
+        let output = TransactionOutput::new(1, Script::new(vec![0x00; 300]));
+        let bytes = output.to_bytes();
+        assert_eq!(bytes[..8], [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        // 300 = 0x012C
+        assert_eq!(bytes[8..11], [0xFD, 0x2C, 0x01]);
+        assert_eq!(bytes.len(), 8 + 3 + 300);
     }
 }